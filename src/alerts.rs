@@ -0,0 +1,422 @@
+// Threshold alerts.
+//
+// A rule fires once its metric has stayed past the threshold for the
+// sustained duration, and resolves once the metric moves back past the
+// threshold by the hysteresis margin. Rules are evaluated per device against
+// sample timestamps, so replayed recordings alert exactly like live GPUs.
+use crate::cli::duration_text;
+use crate::config::{Config, ConfigWatcher, SamplingConfig};
+use crate::csv_log::format_rfc3339;
+use crate::data::{GpuData, GpuInfo, Metric};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Values computed from two metrics of a sample.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivedMetric {
+    /// `memory_used` as a percentage of `memory_total`
+    MemoryPercent,
+    /// `power_usage` as a percentage of `power_limit`
+    PowerPercent,
+}
+
+/// What a rule watches: any sample metric, or a derived percentage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AlertMetric {
+    Derived(DerivedMetric),
+    Metric(Metric),
+}
+
+impl AlertMetric {
+    pub fn value(self, data: &GpuData) -> Option<f64> {
+        match self {
+            AlertMetric::Metric(metric) => data.metric(metric),
+            AlertMetric::Derived(DerivedMetric::MemoryPercent) => {
+                let total = data.memory_total.filter(|total| *total > f64::EPSILON)?;
+                Some(data.memory_used? / total * 100.0)
+            }
+            AlertMetric::Derived(DerivedMetric::PowerPercent) => {
+                let limit = data.power_limit.filter(|limit| *limit > 0.0)?;
+                Some(data.power_usage? / limit * 100.0)
+            }
+        }
+    }
+
+    /// The name used for this metric in rule files, e.g. `gpu_clock`.
+    pub fn key(self) -> String {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(key)) => key,
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for AlertMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertMetric::Metric(metric) => metric.fmt(f),
+            AlertMetric::Derived(DerivedMetric::MemoryPercent) => f.write_str("memory %"),
+            AlertMetric::Derived(DerivedMetric::PowerPercent) => f.write_str("power %"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparator {
+    #[serde(alias = ">")]
+    Above,
+    #[serde(alias = "<")]
+    Below,
+}
+
+impl std::fmt::Display for Comparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Comparator::Above => ">",
+            Comparator::Below => "<",
+        })
+    }
+}
+
+/// What happens when a rule fires or resolves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertAction {
+    /// Show the alert in the GUI while it is firing
+    Highlight,
+    /// Send a desktop notification through `notify-send`
+    Notify,
+    /// Append one line per event to a file
+    Log { path: PathBuf },
+    /// Run a shell command; see `run_command` for what it receives
+    Command { command: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: AlertMetric,
+    pub comparator: Comparator,
+    pub threshold: f64,
+    /// How long the threshold must be crossed before the rule fires
+    #[serde(
+        default,
+        with = "duration_text",
+        skip_serializing_if = "Duration::is_zero"
+    )]
+    pub sustained: Duration,
+    /// How far back past the threshold the metric must go to resolve
+    #[serde(default)]
+    pub hysteresis: f64,
+    #[serde(default = "default_actions")]
+    pub actions: Vec<AlertAction>,
+}
+
+fn default_actions() -> Vec<AlertAction> {
+    vec![AlertAction::Highlight]
+}
+
+impl AlertRule {
+    fn breached(&self, value: f64) -> bool {
+        match self.comparator {
+            Comparator::Above => value > self.threshold,
+            Comparator::Below => value < self.threshold,
+        }
+    }
+
+    fn cleared(&self, value: f64) -> bool {
+        match self.comparator {
+            Comparator::Above => value <= self.threshold - self.hysteresis,
+            Comparator::Below => value >= self.threshold + self.hysteresis,
+        }
+    }
+
+    /// The condition in words, e.g. `temperature > 90`.
+    pub fn condition(&self) -> String {
+        format!("{} {} {}", self.metric, self.comparator, self.threshold)
+    }
+}
+
+/// Read a JSON array of rules.
+pub fn load_rules(path: &Path) -> Result<Vec<AlertRule>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The rules of the config file, followed by those of the `--alerts` file.
+pub fn configured_rules(
+    config: &Config,
+    alerts_file: Option<&Path>,
+) -> Result<Vec<AlertRule>, String> {
+    let mut rules = config.alerts.clone();
+    if let Some(path) = alerts_file {
+        rules.extend(load_rules(path)?);
+    }
+    Ok(rules)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// A rule changing state on one device.
+#[derive(Clone, Debug, Serialize)]
+pub struct AlertEvent {
+    pub rule: String,
+    pub state: AlertState,
+    pub metric: AlertMetric,
+    pub comparator: Comparator,
+    pub threshold: f64,
+    pub value: f64,
+    pub device: GpuInfo,
+    pub sample: GpuData,
+    #[serde(skip)]
+    pub actions: Vec<AlertAction>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum RuleState {
+    #[default]
+    Ok,
+    /// Breached since this timestamp, but not yet for long enough
+    Pending(f64),
+    Firing,
+}
+
+#[derive(Default)]
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    /// Keyed by rule index and device id
+    states: HashMap<(usize, String), RuleState>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules,
+            states: HashMap::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    /// Replace the rules, e.g. after the config file changed. Rules that
+    /// kept their definition and position keep their state, so editing one
+    /// rule does not re-fire the others.
+    pub fn set_rules(&mut self, rules: Vec<AlertRule>) {
+        let old = std::mem::replace(&mut self.rules, rules);
+        self.states
+            .retain(|(index, _), _| old.get(*index) == self.rules.get(*index));
+    }
+
+    /// Pick up edits to the config file, for the modes without a window:
+    /// the rules are applied here and the sampling settings are returned for
+    /// the caller to apply. Errors are reported on stderr.
+    pub fn reload(
+        &mut self,
+        config: &mut ConfigWatcher,
+        alerts_file: Option<&Path>,
+    ) -> Option<SamplingConfig> {
+        let result = config
+            .poll()?
+            .map_err(|e| e.to_string())
+            .and_then(|config| {
+                Ok((
+                    configured_rules(config, alerts_file)?,
+                    config.sampling.clone(),
+                ))
+            });
+        match result {
+            Ok((rules, sampling)) => {
+                self.set_rules(rules);
+                Some(sampling)
+            }
+            Err(e) => {
+                log::warn!("config not reloaded: {e}");
+                None
+            }
+        }
+    }
+
+    /// Advance every rule with a new sample from `device`, returning the
+    /// rules that fired or resolved.
+    pub fn evaluate(&mut self, device: &GpuInfo, sample: &GpuData) -> Vec<AlertEvent> {
+        let mut events = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let state = self
+                .states
+                .entry((index, sample.device_id.clone()))
+                .or_default();
+            let value = rule.metric.value(sample);
+            let event = |state, value| AlertEvent {
+                rule: rule.name.clone(),
+                state,
+                metric: rule.metric,
+                comparator: rule.comparator,
+                threshold: rule.threshold,
+                value,
+                device: device.clone(),
+                sample: sample.clone(),
+                actions: rule.actions.clone(),
+            };
+
+            *state = match (*state, value) {
+                (RuleState::Firing, Some(value)) if rule.cleared(value) => {
+                    events.push(event(AlertState::Resolved, value));
+                    RuleState::Ok
+                }
+                // An unavailable value neither fires nor resolves a rule
+                (RuleState::Firing, _) => RuleState::Firing,
+                (current, Some(value)) if rule.breached(value) => {
+                    let since = match current {
+                        RuleState::Pending(since) => since,
+                        _ => sample.timestamp,
+                    };
+                    if sample.timestamp - since >= rule.sustained.as_secs_f64() {
+                        events.push(event(AlertState::Firing, value));
+                        RuleState::Firing
+                    } else {
+                        RuleState::Pending(since)
+                    }
+                }
+                _ => RuleState::Ok,
+            };
+        }
+        events
+    }
+
+    /// Evaluate a sample and carry out the actions of every resulting event.
+    pub fn process(&mut self, device: &GpuInfo, sample: &GpuData) {
+        for event in self.evaluate(device, sample) {
+            dispatch(&event);
+        }
+    }
+
+    /// Every rule currently firing, with the id of the device it fires on.
+    pub fn firing(&self) -> impl Iterator<Item = (&AlertRule, &str)> {
+        self.states
+            .iter()
+            .filter(|(_, state)| **state == RuleState::Firing)
+            .map(|((index, device_id), _)| (&self.rules[*index], device_id.as_str()))
+    }
+}
+
+/// One line describing an event, as written by the `log` action.
+pub fn format_event(event: &AlertEvent) -> String {
+    let unix_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    format!(
+        "{} {} {} {} {} = {:.2} ({} {})",
+        format_rfc3339(unix_time),
+        match event.state {
+            AlertState::Firing => "FIRING",
+            AlertState::Resolved => "RESOLVED",
+        },
+        event.rule,
+        event.device.bus_id,
+        event.metric,
+        event.value,
+        event.comparator,
+        event.threshold,
+    )
+}
+
+/// Carry out the actions of an event's rule. Highlighting is left to the
+/// GUI, which reads `AlertEngine::firing`. Failures are reported on stderr
+/// so that a broken action never stops monitoring.
+pub fn dispatch(event: &AlertEvent) {
+    for action in &event.actions {
+        let result = match action {
+            AlertAction::Highlight => Ok(()),
+            AlertAction::Notify => notify(event),
+            AlertAction::Log { path } => append_log(path, event),
+            AlertAction::Command { command } => {
+                let command = command.clone();
+                let event = event.clone();
+                // Do not hold up sampling while the command runs
+                std::thread::spawn(move || {
+                    if let Err(e) = run_command(&command, &event) {
+                        log::warn!("alert command '{command}': {e}");
+                    }
+                });
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            log::warn!("alert '{}': {e}", event.rule);
+        }
+    }
+}
+
+fn notify(event: &AlertEvent) -> std::io::Result<()> {
+    let (urgency, title) = match event.state {
+        AlertState::Firing => ("critical", format!("RGM: {}", event.rule)),
+        AlertState::Resolved => ("normal", format!("RGM: {} resolved", event.rule)),
+    };
+    let body = format!(
+        "{} ({}): {} is {:.1} ({} {})",
+        event.device.name,
+        event.device.bus_id,
+        event.metric,
+        event.value,
+        event.comparator,
+        event.threshold
+    );
+    let mut child = Command::new("notify-send")
+        .args(["--app-name=RGM", "--urgency", urgency, &title, &body])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Reap it without blocking, in case the notification daemon is slow
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn append_log(path: &Path, event: &AlertEvent) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", format_event(event))
+}
+
+/// Run `command` with `sh -c` and wait for it. The event is passed as
+/// `RGM_ALERT_*`/`RGM_DEVICE_*` environment variables and as JSON on stdin.
+pub fn run_command(command: &str, event: &AlertEvent) -> std::io::Result<ExitStatus> {
+    let state = match event.state {
+        AlertState::Firing => "firing",
+        AlertState::Resolved => "resolved",
+    };
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("RGM_ALERT_RULE", &event.rule)
+        .env("RGM_ALERT_STATE", state)
+        .env("RGM_ALERT_METRIC", event.metric.key())
+        .env("RGM_ALERT_VALUE", event.value.to_string())
+        .env("RGM_ALERT_THRESHOLD", event.threshold.to_string())
+        .env("RGM_DEVICE_ID", &event.device.bus_id)
+        .env("RGM_DEVICE_NAME", &event.device.name)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let json = serde_json::to_string(event).map_err(std::io::Error::from)?;
+        // A command that ignores stdin may exit before reading it
+        let _ = writeln!(stdin, "{json}");
+    }
+    child.wait()
+}
@@ -1,214 +1,951 @@
-use crate::data::{GpuData, GpuInfo, ProcessInfo};
-use crate::monitor::create_monitor;
-use crossbeam_channel::{bounded, Receiver};
+use crate::alerts::{configured_rules, AlertAction, AlertEngine};
+use crate::cli::{format_duration, parse_duration, CliOptions};
+use crate::config::{ConfigWatcher, Theme, UiConfig};
+use crate::csv_log::{CsvOptions, CsvWriter};
+use crate::data::{GpuData, Metric, MetricError, ProcessInfo};
+use crate::history::Resolution;
+use crate::monitor::{BackendFailure, GpuMonitor};
+use crate::processes::{visible_processes, SortKey};
+use crate::recording::{load_monitors, load_supervised, Recorder};
+use crate::sampler::{DeviceState, DeviceStatus, ErrorLog, Sampler};
+use crate::settings::{self, Settings};
+use crate::signals::{self, Signal};
 use eframe::egui::{self, Color32};
 use egui_plot::{Legend, Line, Plot, PlotPoints};
-use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
-use std::{thread, time::Duration};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 // Main application structure
 pub struct RgmApp {
-    data: Arc<Mutex<VecDeque<GpuData>>>,
-    receiver: Receiver<(GpuData, Vec<ProcessInfo>)>,
-    display_duration: f64,
-    gpu_info: GpuInfo,
-    processes: Arc<Mutex<Vec<ProcessInfo>>>,
+    // Sampling threads, device histories, alerts and CSV logging
+    sampler: Sampler,
+    selected_device: usize,
+    // Backends that were probed but failed; with no device at all they are
+    // shown as a diagnostics screen
+    backend_failures: Vec<BackendFailure>,
+    // Kept to detect GPUs again from the diagnostics screen
+    options: CliOptions,
+    settings: Settings,
+    // Settings as read from storage, plus the edits made in the settings
+    // dialog; `save` keeps overrides from the command line or config out
+    stored: Settings,
+    show_settings: bool,
+    // Text typed into the process table filter
+    process_filter: String,
+    // Signal picked from a process's context menu, awaiting confirmation
+    pending_signal: Option<PendingSignal>,
+    // Outcome of the last signal sent, shown above the process table
+    signal_result: Option<Result<String, String>>,
+    alerts_file: Option<PathBuf>,
+    // Config file, re-read when it changes; `ui` is the layout in effect
+    config: ConfigWatcher,
+    config_error: Option<String>,
+    ui: UiConfig,
+    // Where the "Start CSV log" button writes
+    csv_path: Option<PathBuf>,
+    csv_options: CsvOptions,
+    csv_error: Option<String>,
 }
 
 impl RgmApp {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let (sender, receiver) = bounded(100);
-        let data = Arc::new(Mutex::new(VecDeque::with_capacity(120)));
-        let processes = Arc::new(Mutex::new(Vec::new()));
-
-        let monitor = create_monitor().expect("Failed to find and initialize a GPU monitor!");
-        let gpu_info = monitor.get_static_info();
-
-        thread::spawn(move || loop {
-            match monitor.sample() {
-                Ok((gpu_data, proc_infos)) => {
-                    if sender.send((gpu_data, proc_infos)).is_err() {
-                        break;
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        options: &CliOptions,
+        alerts: AlertEngine,
+        config: ConfigWatcher,
+    ) -> Self {
+        let (report, probe, rescan) = load_supervised(options);
+        let backend_failures = report.failures;
+        let monitors = report.monitors;
+
+        let stored = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<Settings>(storage, Settings::STORAGE_KEY))
+            .unwrap_or_default()
+            .clamped();
+        let settings = stored.clone().with_cli(options);
+
+        // Without devices the recording starts once detection finds some
+        let recorder = options
+            .record
+            .as_deref()
+            .filter(|_| !monitors.is_empty())
+            .and_then(|path| create_recorder(path, &monitors));
+
+        let mut csv_error = None;
+        let csv_log = options.csv.as_ref().and_then(|path| {
+            CsvWriter::create(path, options.csv_options.clone())
+                .map_err(|e| csv_error = Some(format!("{}: {e}", path.display())))
+                .ok()
+        });
+        let sampler = Sampler::start(
+            monitors,
+            settings.sample_interval,
+            settings.history,
+            recorder,
+            csv_log,
+            alerts,
+        );
+        sampler.supervise(probe, rescan);
+
+        let ui = config.config().ui.clone();
+        apply_theme(&cc.egui_ctx, ui.theme);
+        let selected_device = preferred_device(&sampler.devices, &ui);
+
+        Self {
+            sampler,
+            selected_device,
+            backend_failures,
+            options: options.clone(),
+            settings,
+            stored,
+            show_settings: false,
+            process_filter: String::new(),
+            pending_signal: None,
+            signal_result: None,
+            alerts_file: options.alerts.clone(),
+            config,
+            config_error: None,
+            ui,
+            csv_path: options.csv.clone(),
+            csv_options: options.csv_options.clone(),
+            csv_error,
+        }
+    }
+
+    /// Apply an edited config file. Backends, devices, the exporter and CSV
+    /// logging are only read at startup; everything else takes effect now.
+    fn reload_config(&mut self, ctx: &egui::Context) {
+        let config = match self.config.poll() {
+            None => return,
+            Some(Ok(config)) => config.clone(),
+            Some(Err(e)) => {
+                self.config_error = Some(e.to_string());
+                return;
+            }
+        };
+        self.config_error = None;
+
+        match configured_rules(&config, self.alerts_file.as_deref()) {
+            Ok(rules) => self.sampler.alerts.lock().unwrap().set_rules(rules),
+            Err(e) => self.config_error = Some(e),
+        }
+        let sampling = self.options.sampling_overrides.reloaded(&config.sampling);
+        if let Some(interval) = sampling.interval {
+            self.settings.sample_interval = interval;
+        }
+        if let Some(history) = sampling.history {
+            self.settings.history = history;
+        }
+
+        apply_theme(ctx, config.ui.theme);
+        if config.ui.window_size != self.ui.window_size {
+            let [width, height] = config.ui.window_size;
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(width, height)));
+        }
+        self.ui = config.ui;
+    }
+
+    /// List the firing alerts whose rules ask to be highlighted, by device;
+    /// clicking one selects that device.
+    fn alert_banner(&mut self, ui: &mut egui::Ui) {
+        let mut firing: Vec<(usize, String)> = self
+            .sampler
+            .alerts
+            .lock()
+            .unwrap()
+            .firing()
+            .filter(|(rule, _)| rule.actions.contains(&AlertAction::Highlight))
+            .filter_map(|(rule, device_id)| {
+                let index = self.sampler.device_index(device_id)?;
+                Some((index, format!("{}: {}", rule.name, rule.condition())))
+            })
+            .collect();
+        if firing.is_empty() {
+            return;
+        }
+        firing.sort();
+
+        egui::Frame::group(ui.style())
+            .stroke(egui::Stroke::new(1.0, Color32::RED))
+            .show(ui, |ui| {
+                for (index, text) in firing {
+                    let label = egui::RichText::new(format!("⚠ GPU {index} · {text}"))
+                        .color(Color32::RED)
+                        .strong();
+                    if ui
+                        .add(egui::Label::new(label).sense(egui::Sense::click()))
+                        .clicked()
+                    {
+                        self.selected_device = index;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Error sampling GPU data: {}", e);
+            });
+        ui.add_space(8.0);
+    }
+
+    /// Error counts and the latest error from the sampling threads, along
+    /// the bottom of the window.
+    fn status_bar(&mut self, ctx: &egui::Context) {
+        let mut clear = false;
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let errors = &self.sampler.errors;
+                let Some(last) = &errors.last else {
+                    ui.label(egui::RichText::new("No errors").weak());
+                    return;
+                };
+                ui.colored_label(Color32::YELLOW, format!("⚠ {}", errors.summary()));
+                ui.label(format!("Last: {last}"));
+                clear = ui.small_button("Clear").clicked();
+            });
+        });
+        if clear {
+            self.sampler.errors = ErrorLog::default();
+        }
+    }
+
+    /// Shown instead of the monitor while no GPU is found: why each backend
+    /// failed, what to do about it, and a way to try again.
+    fn diagnostics(&mut self, ui: &mut egui::Ui) {
+        ui.heading("⚠ No GPU found");
+        ui.label("None of the GPU backends found a device RGM can monitor.");
+        ui.add_space(8.0);
+        if self.backend_failures.is_empty() {
+            ui.label(
+                "No backend was probed; check --backend and backends.enabled in the config file.",
+            );
+        }
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                for failure in &self.backend_failures {
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(egui::RichText::new(failure.backend.name()).strong());
+                        ui.colored_label(Color32::RED, failure.error.to_string());
+                        ui.label(failure.remedy());
+                    });
+                    ui.add_space(4.0);
                 }
+            });
+        ui.add_space(8.0);
+        if ui.button("🔄 Retry detection").clicked() {
+            self.detect_devices();
+        }
+    }
+
+    /// Probe the backends again, e.g. after a driver was loaded, and monitor
+    /// whatever is found.
+    fn detect_devices(&mut self) {
+        let report = load_monitors(&self.options);
+        self.backend_failures = report.failures;
+        if report.monitors.is_empty() {
+            return;
+        }
+        if let Some(path) = &self.options.record {
+            let mut recorder = self.sampler.recorder.lock().unwrap();
+            if recorder.is_none() {
+                *recorder = create_recorder(path, &report.monitors);
+            }
+        }
+        for monitor in report.monitors {
+            self.sampler.add(monitor);
+        }
+        self.selected_device = preferred_device(&self.sampler.devices, &self.ui);
+    }
+
+    /// Ask before sending the signal picked from a context menu.
+    fn confirm_signal(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.pending_signal else {
+            return;
+        };
+        let mut confirmed = None;
+        egui::Window::new("Send signal")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Send {} to {} (PID {})?",
+                    pending.signal, pending.name, pending.pid
+                ));
+                ui.label(egui::RichText::new(pending.signal.description()).weak());
+                ui.horizontal(|ui| {
+                    if ui.button(format!("Send {}", pending.signal)).clicked() {
+                        confirmed = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        confirmed = Some(false);
+                    }
+                });
+            });
+
+        match confirmed {
+            Some(true) => {
+                self.signal_result = Some(
+                    signals::send(pending.pid, pending.signal)
+                        .map(|()| {
+                            format!(
+                                "Sent {} to {} ({})",
+                                pending.signal, pending.name, pending.pid
+                            )
+                        })
+                        .map_err(|e| e.to_string()),
+                );
+                self.pending_signal = None;
             }
-            thread::sleep(Duration::from_millis(100));
+            Some(false) => self.pending_signal = None,
+            None => {}
+        }
+    }
+
+    /// Start or stop CSV logging from the UI. Without `--csv`, each start
+    /// writes a new `rgm-<unix time>.csv` in the working directory.
+    fn toggle_csv_log(&mut self) {
+        let mut csv_log = self.sampler.csv_log.lock().unwrap();
+        if csv_log.take().is_some() {
+            return;
+        }
+        let path = self.csv_path.clone().unwrap_or_else(|| {
+            let secs = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            PathBuf::from(format!("rgm-{secs}.csv"))
         });
+        match CsvWriter::create(&path, self.csv_options.clone()) {
+            Ok(writer) => {
+                *csv_log = Some(writer);
+                self.csv_error = None;
+            }
+            Err(e) => self.csv_error = Some(format!("{}: {e}", path.display())),
+        }
+    }
+}
 
-        let mut style = (*cc.egui_ctx.style()).clone();
-        style.visuals.dark_mode = true;
-        cc.egui_ctx.set_style(style);
+fn apply_theme(ctx: &egui::Context, theme: Theme) {
+    ctx.set_visuals(match theme {
+        Theme::Dark => egui::Visuals::dark(),
+        Theme::Light => egui::Visuals::light(),
+    });
+}
 
-        Self {
-            data,
-            receiver,
-            display_duration: 10.0,
-            gpu_info,
-            processes,
+type Mapper = fn(&GpuData) -> Option<f64>;
+
+// One of the stacked metric plots: its title, Y unit and the series it draws
+struct MetricPlot {
+    /// Stable key for the plot's memory and the saved toggles
+    id: &'static str,
+    title: &'static str,
+    unit: &'static str,
+    /// Top of the Y range, always shown, for percentages
+    y_max: Option<f64>,
+    series: &'static [(&'static str, Mapper, Color32)],
+}
+
+/// Index of the device `ui.device` selects, or the first one.
+fn preferred_device(devices: &[DeviceState], ui: &UiConfig) -> usize {
+    ui.device
+        .as_deref()
+        .and_then(|pattern| devices.iter().position(|d| d.gpu_info.matches(pattern)))
+        .unwrap_or(0)
+}
+
+/// Start recording the devices of `monitors`. Failures are logged; the GUI
+/// runs without recording then.
+fn create_recorder(path: &Path, monitors: &[Box<dyn GpuMonitor>]) -> Option<Recorder> {
+    let infos = monitors.iter().map(|m| m.get_static_info()).collect();
+    match Recorder::create(path, infos) {
+        Ok(recorder) => Some(recorder),
+        Err(e) => {
+            log::error!("cannot record to {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+// A signal the user asked to send, shown for confirmation first
+struct PendingSignal {
+    pid: u32,
+    name: String,
+    signal: Signal,
+}
+
+/// Context menu of a process row: one entry per signal.
+fn signal_menu(ui: &mut egui::Ui, process: &ProcessInfo, requested: &mut Option<PendingSignal>) {
+    ui.label(egui::RichText::new(format!("{} ({})", process.name, process.pid)).strong());
+    ui.separator();
+    for signal in Signal::ALL {
+        let button = ui
+            .button(format!("Send {signal}"))
+            .on_hover_text(signal.description());
+        if button.clicked() {
+            *requested = Some(PendingSignal {
+                pid: process.pid,
+                name: process.name.clone(),
+                signal,
+            });
+        }
+    }
+}
+
+// Process grid headings and the key each one sorts by
+const PROCESS_COLUMNS: [(&str, SortKey); 12] = [
+    ("PID", SortKey::Pid),
+    ("Name", SortKey::Name),
+    ("User", SortKey::User),
+    ("Type", SortKey::Type),
+    ("Memory (MB)", SortKey::GpuMemory),
+    ("GPU %", SortKey::Gpu),
+    ("SM %", SortKey::Sm),
+    ("Enc %", SortKey::Encoder),
+    ("Dec %", SortKey::Decoder),
+    ("CPU %", SortKey::Cpu),
+    ("RSS (MB)", SortKey::HostMemory),
+    ("Command", SortKey::Command),
+];
+
+const METRIC_PLOTS: &[MetricPlot] = &[
+    MetricPlot {
+        id: "utilization",
+        title: "Utilization",
+        unit: "%",
+        y_max: Some(100.0),
+        series: &[("GPU", |d| d.metric(Metric::Utilization), Color32::GREEN)],
+    },
+    MetricPlot {
+        id: "memory",
+        title: "Memory",
+        unit: "GB",
+        y_max: None,
+        series: &[
+            ("Used", |d| d.memory_used, Color32::from_rgb(0, 128, 255)),
+            ("Total", |d| d.memory_total, Color32::GRAY),
+        ],
+    },
+    MetricPlot {
+        id: "temperature",
+        title: "Temperature",
+        unit: "°C",
+        y_max: None,
+        series: &[(
+            "GPU",
+            |d| d.metric(Metric::Temperature),
+            Color32::from_rgb(255, 128, 0),
+        )],
+    },
+    MetricPlot {
+        id: "power",
+        title: "Power",
+        unit: "W",
+        y_max: None,
+        series: &[
+            ("Draw", |d| d.power_usage, Color32::from_rgb(255, 0, 128)),
+            ("Limit", |d| d.power_limit, Color32::GRAY),
+        ],
+    },
+    MetricPlot {
+        id: "clocks",
+        title: "Clocks",
+        unit: "MHz",
+        y_max: None,
+        series: &[
+            (
+                "GPU",
+                |d| d.metric(Metric::GpuClock),
+                Color32::from_rgb(255, 215, 0),
+            ),
+            (
+                "Memory",
+                |d| d.metric(Metric::MemoryClock),
+                Color32::from_rgb(0, 200, 200),
+            ),
+        ],
+    },
+    MetricPlot {
+        id: "fan",
+        title: "Fan",
+        unit: "%",
+        y_max: Some(100.0),
+        series: &[(
+            "Fan",
+            |d| d.metric(Metric::FanSpeed),
+            Color32::from_rgb(135, 206, 250),
+        )],
+    },
+    MetricPlot {
+        id: "pcie",
+        title: "PCIe Throughput",
+        unit: "MB/s",
+        y_max: None,
+        series: &[
+            (
+                "TX",
+                |d| d.pcie_throughput_tx,
+                Color32::from_rgb(180, 120, 255),
+            ),
+            (
+                "RX",
+                |d| d.pcie_throughput_rx,
+                Color32::from_rgb(120, 220, 120),
+            ),
+        ],
+    },
+];
+
+/// A duration slider over `range`, shown and edited in `parse_duration`
+/// notation.
+fn duration_slider(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Duration,
+    range: std::ops::RangeInclusive<Duration>,
+) -> bool {
+    let mut seconds = value.as_secs_f64();
+    let response = ui.add(
+        egui::Slider::new(
+            &mut seconds,
+            range.start().as_secs_f64()..=range.end().as_secs_f64(),
+        )
+        .logarithmic(true)
+        .text(label)
+        .custom_formatter(|v, _| format_duration(Duration::from_secs_f64(v)))
+        .custom_parser(|text| parse_duration(text).ok().map(|d| d.as_secs_f64())),
+    );
+    *value = Duration::from_secs_f64(seconds);
+    response.changed()
+}
+
+/// Show one metric: greyed out when the device does not provide it, red with
+/// the error on hover when the read failed.
+fn metric_label(ui: &mut egui::Ui, name: &str, value: Option<String>, error: Option<&MetricError>) {
+    match (value, error) {
+        (Some(value), _) => {
+            ui.label(format!("{name}: {value}"));
+        }
+        (None, Some(error)) => {
+            ui.colored_label(Color32::RED, format!("{name}: error"))
+                .on_hover_text(error.message.as_str());
+        }
+        (None, None) => {
+            ui.label(egui::RichText::new(format!("{name}: N/A")).weak())
+                .on_hover_text("Not supported by this device");
         }
     }
 }
 
 impl eframe::App for RgmApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let settings = self.settings.persisted(&self.stored);
+        eframe::set_value(storage, Settings::STORAGE_KEY, &settings);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        while let Ok((gpu_data, proc_infos)) = self.receiver.try_recv() {
-            let mut data = self.data.lock().unwrap();
-            let now = gpu_data.timestamp;
-            let window_start_time = (now - self.display_duration).max(0.0);
-            data.push_back(gpu_data);
-            while data
-                .front()
-                .is_some_and(|d| d.timestamp < window_start_time)
-            {
-                data.pop_front();
-            }
-            let mut processes = self.processes.lock().unwrap();
-            *processes = proc_infos;
+        self.sampler.update(self.settings.history);
+        self.reload_config(ctx);
+
+        egui::Window::new("Settings")
+            .open(&mut self.show_settings)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if duration_slider(
+                    ui,
+                    "Sampling interval",
+                    &mut self.settings.sample_interval,
+                    settings::MIN_SAMPLE_INTERVAL..=settings::MAX_SAMPLE_INTERVAL,
+                ) {
+                    self.stored.sample_interval = self.settings.sample_interval;
+                }
+                if duration_slider(
+                    ui,
+                    "History",
+                    &mut self.settings.history,
+                    settings::MIN_HISTORY..=settings::MAX_HISTORY,
+                ) {
+                    self.stored.history = self.settings.history;
+                }
+            });
+        self.sampler.set_interval(self.settings.sample_interval);
+        self.confirm_signal(ctx);
+        self.status_bar(ctx);
+
+        if self.sampler.devices.is_empty() {
+            egui::CentralPanel::default().show(ctx, |ui| self.diagnostics(ui));
+            // GPUs attached meanwhile are picked up by the sampler's rescans
+            ctx.request_repaint_after(Duration::from_secs(1));
+            return;
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("🚀 GPU Monitor");
+            ui.horizontal(|ui| {
+                ui.heading("🚀 GPU Monitor");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let logging_to = self
+                        .sampler
+                        .csv_log
+                        .lock()
+                        .unwrap()
+                        .as_ref()
+                        .map(|csv| csv.path().display().to_string());
+                    let button = match &logging_to {
+                        Some(_) => "⏹ Stop CSV log",
+                        None => "⏺ Start CSV log",
+                    };
+                    if ui.button("⚙ Settings").clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                    if ui.button(button).clicked() {
+                        self.toggle_csv_log();
+                    }
+                    if let Some(path) = logging_to {
+                        ui.label(format!("Logging to {path}"));
+                    } else if let Some(error) = &self.csv_error {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                });
+            });
+
+            // Overview of every device, side by side; click one to inspect it
+            if self.sampler.devices.len() > 1 {
+                ui.horizontal_wrapped(|ui| {
+                    for (index, device) in self.sampler.devices.iter().enumerate() {
+                        let latest = match device.status {
+                            DeviceStatus::Online => device.history.latest(),
+                            DeviceStatus::Offline(_) => None,
+                        };
+                        let summary = latest
+                            .map(|d| {
+                                let util =
+                                    d.utilization.map_or("-".to_string(), |u| format!("{u}%"));
+                                let temp =
+                                    d.temperature.map_or("-".to_string(), |t| format!("{t}°C"));
+                                format!("{util} · {temp}")
+                            })
+                            .unwrap_or_else(|| match device.status {
+                                DeviceStatus::Online => "waiting…".to_string(),
+                                DeviceStatus::Offline(_) => "offline".to_string(),
+                            });
+                        let label = format!("GPU {index} · {}\n{summary}", device.gpu_info.name);
+                        ui.selectable_value(&mut self.selected_device, index, label)
+                            .on_hover_text(format!(
+                                "{} · {}",
+                                device.gpu_info.vendor, device.gpu_info.bus_id
+                            ));
+                    }
+                });
+            }
+
+            if !self.backend_failures.is_empty() {
+                ui.collapsing("Unavailable backends", |ui| {
+                    for failure in &self.backend_failures {
+                        let failure = format!("{}: {}", failure.backend, failure.error);
+                        ui.label(egui::RichText::new(failure).weak());
+                    }
+                });
+            }
+
+            if let Some(error) = &self.config_error {
+                ui.colored_label(Color32::RED, format!("Config not reloaded: {error}"));
+            }
+            self.alert_banner(ui);
+
+            let device = &self.sampler.devices[self.selected_device];
             ui.label(format!(
                 "{} - Driver: {}",
-                self.gpu_info.name, self.gpu_info.driver_version
+                device.gpu_info.name, device.gpu_info.driver_version
             ));
+            if let DeviceStatus::Offline(error) = &device.status {
+                ui.colored_label(
+                    Color32::RED,
+                    format!("⚠ Device offline: {error}. Reconnecting…"),
+                );
+            }
             ui.add_space(8.0);
 
-            let data_guard = self.data.lock().unwrap();
-            let latest = data_guard.back();
+            let history = &device.history;
+            let latest = history.latest();
 
             if let Some(latest) = latest {
                 egui::Frame::group(ui.style()).show(ui, |ui| {
                     ui.horizontal(|ui| {
                         ui.vertical(|ui| {
-                            ui.label(
-                                egui::RichText::new(format!(
-                                    "GPU Utilization: {}%",
-                                    latest.utilization
-                                ))
-                                .color(Color32::GREEN)
-                                .size(22.0)
-                                .strong(),
+                            let utilization = match latest.utilization {
+                                Some(util) => {
+                                    egui::RichText::new(format!("GPU Utilization: {util}%"))
+                                        .color(Color32::GREEN)
+                                }
+                                None => egui::RichText::new("GPU Utilization: N/A").weak(),
+                            };
+                            ui.label(utilization.size(22.0).strong());
+                            metric_label(
+                                ui,
+                                "Temperature",
+                                latest.temperature.map(|t| format!("{t}°C")),
+                                latest.error(Metric::Temperature),
+                            );
+                            metric_label(
+                                ui,
+                                "Fan Speed",
+                                latest.fan_speed.map(|f| format!("{f}%")),
+                                latest.error(Metric::FanSpeed),
                             );
-                            ui.label(format!("Temperature: {}°C", latest.temperature));
-                            ui.label(format!("Fan Speed: {}%", latest.fan_speed));
                         });
                         ui.separator();
                         ui.vertical(|ui| {
-                            ui.label(format!(
-                                "Memory: {:.2}/{:.2} GB",
-                                latest.memory_used, latest.memory_total
-                            ));
-                            ui.label(format!(
-                                "Power: {:.2}/{:.2} W",
-                                latest.power_usage, latest.power_limit
-                            ));
-                            ui.label(format!("GPU Clock: {} MHz", latest.gpu_clock));
-                            ui.label(format!("Memory Clock: {} MHz", latest.memory_clock));
+                            metric_label(
+                                ui,
+                                "Memory",
+                                latest
+                                    .memory_used
+                                    .zip(latest.memory_total)
+                                    .map(|(used, total)| format!("{used:.2}/{total:.2} GB")),
+                                latest
+                                    .error(Metric::MemoryUsed)
+                                    .or(latest.error(Metric::MemoryTotal)),
+                            );
+                            metric_label(
+                                ui,
+                                "Power",
+                                latest.power_usage.map(|usage| match latest.power_limit {
+                                    Some(limit) => format!("{usage:.2}/{limit:.2} W"),
+                                    None => format!("{usage:.2} W"),
+                                }),
+                                latest.error(Metric::PowerUsage),
+                            );
+                            metric_label(
+                                ui,
+                                "GPU Clock",
+                                latest
+                                    .gpu_clock
+                                    .map(|c| match device.gpu_info.max_gpu_clock {
+                                        Some(max) => format!("{c}/{max} MHz"),
+                                        None => format!("{c} MHz"),
+                                    }),
+                                latest.error(Metric::GpuClock),
+                            );
+                            metric_label(
+                                ui,
+                                "Memory Clock",
+                                latest.memory_clock.map(|c| format!("{c} MHz")),
+                                latest.error(Metric::MemoryClock),
+                            );
                         });
                         ui.separator();
                         ui.vertical(|ui| {
                             ui.label(format!(
                                 "PCIe: Gen {} x{}",
-                                self.gpu_info.pcie_gen, self.gpu_info.pcie_width
+                                device.gpu_info.pcie_gen, device.gpu_info.pcie_width
                             ));
-                            ui.label(format!("PCIe TX: {:.2} MB/s", latest.pcie_throughput_tx));
-                            ui.label(format!("PCIe RX: {:.2} MB/s", latest.pcie_throughput_rx));
+                            metric_label(
+                                ui,
+                                "PCIe TX",
+                                latest.pcie_throughput_tx.map(|tx| format!("{tx:.2} MB/s")),
+                                latest.error(Metric::PcieThroughputTx),
+                            );
+                            metric_label(
+                                ui,
+                                "PCIe RX",
+                                latest.pcie_throughput_rx.map(|rx| format!("{rx:.2} MB/s")),
+                                latest.error(Metric::PcieThroughputRx),
+                            );
                         });
                     });
                 });
+                if let Some(ms) = latest.collection_time {
+                    ui.label(egui::RichText::new(format!("Sampled in {ms:.1} ms")).weak());
+                }
             }
 
             ui.add_space(12.0);
             ui.separator();
-            ui.heading("📈 Real-time GPU Metrics (Last 10 Seconds)");
-
-            let latest_timestamp = data_guard.back().map_or(0.0, |d| d.timestamp);
-            let to_relative_points = |mapper: Box<dyn Fn(&GpuData) -> f64>| -> PlotPoints {
-                data_guard
-                    .iter()
-                    .map(|data| {
-                        let x = latest_timestamp - data.timestamp;
-                        [x.max(0.0), mapper(data)]
-                    })
-                    .collect()
+            ui.heading(format!(
+                "📈 Real-time GPU Metrics (Last {})",
+                format_duration(self.settings.history)
+            ));
+
+            // Plots of metrics the device does not provide are left out
+            let available = |plot: &MetricPlot| {
+                latest.is_some_and(|l| plot.series.iter().any(|(_, value, _)| value(l).is_some()))
             };
-            let gpu_util_points: PlotPoints =
-                to_relative_points(Box::new(|d| d.utilization as f64));
-            let memory_points: PlotPoints = to_relative_points(Box::new(|d| {
-                if d.memory_total > f64::EPSILON {
-                    d.memory_used / d.memory_total * 100.0
-                } else {
-                    0.0
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Plots:");
+                for plot in METRIC_PLOTS {
+                    let mut shown = !self.settings.hidden_plots.contains(plot.id);
+                    let response = ui
+                        .add_enabled(available(plot), egui::Checkbox::new(&mut shown, plot.title));
+                    if response.changed() {
+                        if shown {
+                            self.settings.hidden_plots.remove(plot.id);
+                        } else {
+                            self.settings.hidden_plots.insert(plot.id.to_string());
+                        }
+                    }
+                    response.on_disabled_hover_text("Not supported by this device");
                 }
-            }));
-            let temp_points: PlotPoints = to_relative_points(Box::new(|d| d.temperature as f64));
-            let power_points: PlotPoints = data_guard
+            });
+            let visible_plots: Vec<&MetricPlot> = METRIC_PLOTS
                 .iter()
-                .filter(|data| data.power_limit > 0.0)
-                .map(|data| {
-                    let x = latest_timestamp - data.timestamp;
-                    [x.max(0.0), data.power_usage / data.power_limit * 100.0]
-                })
+                .filter(|plot| !self.settings.hidden_plots.contains(plot.id) && available(plot))
                 .collect();
 
-            Plot::new("gpu_metrics_plot")
-                .view_aspect(2.5)
-                .legend(Legend::default())
-                .include_y(0.0)
-                .include_y(100.0)
-                .include_x(0.0)
-                .include_x(self.display_duration)
-                .x_axis_label("Seconds Ago (0 = now)")
-                .show_x(true)
-                .show_y(true)
-                .show(ui, |plot_ui| {
-                    plot_ui
-                        .line(Line::new("GPU Utilization", gpu_util_points).color(Color32::GREEN));
-                    plot_ui.line(
-                        Line::new("Memory Usage (%)", memory_points)
-                            .color(Color32::from_rgb(0, 128, 255)),
-                    );
-                    plot_ui.line(
-                        Line::new("Temperature (°C)", temp_points)
-                            .color(Color32::from_rgb(255, 128, 0)),
-                    );
-                    plot_ui.line(
-                        Line::new("Power Usage (%)", power_points)
-                            .color(Color32::from_rgb(255, 0, 128)),
-                    );
+            let history_span = self.settings.history.as_secs_f64();
+            let width_points = ui.available_width().max(1.0) as f64;
+            let latest_timestamp = latest.map_or(0.0, |d| d.timestamp);
+            let mut resolution = Resolution::Raw;
+            // Panning or zooming one plot moves them all along the time axis
+            let link_group = egui::Id::new("metric_plots");
+
+            egui::ScrollArea::vertical()
+                .id_salt("metric_plots")
+                .max_height(480.0)
+                .show(ui, |ui| {
+                    for (index, plot) in visible_plots.iter().enumerate() {
+                        ui.label(egui::RichText::new(plot.title).strong());
+                        let unit = plot.unit;
+                        let mut chart = Plot::new(plot.id)
+                            .height(self.ui.plot_height)
+                            .legend(Legend::default())
+                            .include_y(0.0)
+                            .include_x(0.0)
+                            .include_x(history_span)
+                            .y_axis_label(unit)
+                            .link_axis(link_group, [true, false])
+                            .link_cursor(link_group, [true, false])
+                            .label_formatter(move |name, value| {
+                                if name.is_empty() {
+                                    format!("{:.1} s ago", value.x)
+                                } else {
+                                    format!("{name}\n{:.1} s ago\n{:.2} {unit}", value.x, value.y)
+                                }
+                            });
+                        if let Some(y_max) = plot.y_max {
+                            chart = chart.include_y(y_max);
+                        }
+                        if index + 1 == visible_plots.len() {
+                            chart = chart.x_axis_label("Seconds Ago (0 = now)");
+                        }
+                        chart.show(ui, |plot_ui| {
+                            // Read the level of detail that fits the zoomed-in
+                            // range into roughly one point per screen point
+                            let bounds = plot_ui.plot_bounds();
+                            let span = bounds.max()[0].clamp(1.0, history_span);
+                            let visible = (span - bounds.min()[0].max(0.0)).clamp(1.0, span);
+                            let max_points = (width_points * span / visible) as usize;
+                            for (name, value, color) in plot.series {
+                                let (level, series) = history.series(span, max_points, value);
+                                resolution = level;
+                                if series.is_empty() {
+                                    continue;
+                                }
+                                let points: PlotPoints = series
+                                    .iter()
+                                    .map(|p| [(latest_timestamp - p.timestamp).max(0.0), p.avg])
+                                    .collect();
+                                plot_ui.line(Line::new(*name, points).color(*color));
+                            }
+                        });
+                    }
                 });
+            let resolution = match resolution {
+                Resolution::Raw => "raw samples".to_string(),
+                Resolution::Rollup(seconds) => {
+                    format!("{} averages", format_duration(Duration::from_secs(seconds)))
+                }
+            };
+            ui.label(egui::RichText::new(format!("Resolution: {resolution}")).weak());
 
             ui.add_space(12.0);
             ui.separator();
             ui.heading("🧩 GPU Processes");
-            egui::ScrollArea::vertical()
+            ui.horizontal(|ui| {
+                ui.label("Filter:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.process_filter)
+                        .hint_text("PID, name, user or command"),
+                );
+                if !self.process_filter.is_empty() && ui.button("✖").clicked() {
+                    self.process_filter.clear();
+                }
+            });
+            match &self.signal_result {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(Color32::RED, error);
+                }
+                None => {}
+            }
+            let processes = visible_processes(
+                &device.processes,
+                &self.process_filter,
+                self.settings.process_sort,
+            );
+            let sort = &mut self.settings.process_sort;
+            let mut requested = None;
+            egui::ScrollArea::both()
+                .id_salt("processes")
                 .max_height(200.0)
                 .show(ui, |ui| {
-                    let processes = self.processes.lock().unwrap();
                     egui::Grid::new("processes_grid")
                         .striped(true)
                         .spacing([12.0, 6.0])
                         .show(ui, |ui| {
-                            ui.label(egui::RichText::new("PID").strong());
-                            ui.label(egui::RichText::new("Name").strong());
-                            ui.label(egui::RichText::new("Memory (MB)").strong());
+                            // Click a heading to sort by it, again to reverse
+                            for (title, key) in PROCESS_COLUMNS {
+                                let text = if sort.key == key {
+                                    let arrow = if sort.descending { "⏷" } else { "⏶" };
+                                    format!("{title} {arrow}")
+                                } else {
+                                    title.to_string()
+                                };
+                                let heading = egui::RichText::new(text).strong();
+                                if ui.selectable_label(sort.key == key, heading).clicked() {
+                                    sort.select(key);
+                                }
+                            }
                             ui.end_row();
-                            for proc in processes.iter() {
-                                ui.label(proc.pid.to_string());
-                                ui.label(&proc.name);
-                                ui.label(format!(
-                                    "{:.1}",
-                                    proc.memory_usage as f64 / 1024.0 / 1024.0
-                                ));
+                            for proc in &processes {
+                                let percent = |value: Option<f32>| {
+                                    value.map_or_else(|| "-".to_string(), |v| format!("{v:.0}"))
+                                };
+                                let mib =
+                                    |bytes: u64| format!("{:.1}", bytes as f64 / 1024.0 / 1024.0);
+                                // Long command lines are cut; hover for all of it
+                                let command: String = proc.command_line.chars().take(60).collect();
+                                let cells = [
+                                    ui.label(proc.pid.to_string()),
+                                    ui.label(&proc.name),
+                                    ui.label(&proc.user),
+                                    ui.label(proc.process_type.map_or("-", |t| t.label())),
+                                    ui.label(mib(proc.memory_usage)),
+                                    ui.label(format!("{:.1}", proc.gpu_utilization)),
+                                    ui.label(percent(proc.sm_utilization)),
+                                    ui.label(percent(proc.encoder_utilization)),
+                                    ui.label(percent(proc.decoder_utilization)),
+                                    ui.label(format!("{:.1}", proc.cpu_percent)),
+                                    ui.label(mib(proc.host_memory)),
+                                    ui.label(command).on_hover_text(&proc.command_line),
+                                ];
+                                // Right-click anywhere on the row to signal it
+                                // Recorded processes are not live
+                                if self.options.replay.is_none() {
+                                    for cell in cells {
+                                        cell.context_menu(|ui| {
+                                            signal_menu(ui, proc, &mut requested)
+                                        });
+                                    }
+                                }
                                 ui.end_row();
                             }
                         });
+                    if processes.is_empty() && !device.processes.is_empty() {
+                        ui.label(egui::RichText::new("No processes match the filter").weak());
+                    }
                 });
+            if requested.is_some() {
+                self.pending_signal = requested;
+            }
         });
 
         ctx.request_repaint();
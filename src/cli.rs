@@ -0,0 +1,468 @@
+// Command-line argument parsing
+use crate::config::SamplingConfig;
+use crate::csv_log::{CsvColumn, CsvOptions};
+use crate::exporter::DEFAULT_LISTEN;
+use crate::logging::{self, DEFAULT_LEVEL};
+use crate::monitor::BackendRegistry;
+use crate::settings::{MAX_HISTORY, MAX_SAMPLE_INTERVAL, MIN_HISTORY, MIN_SAMPLE_INTERVAL};
+use crate::signals::Signal;
+use log::LevelFilter;
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Headless and exporter sampling interval when `--interval` is not given.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const USAGE: &str = "\
+Usage: rgm [OPTIONS]
+       rgm kill [-s <SIGNAL>] <PID>...
+
+Without options, opens the graphical monitor. Defaults for most options can
+be set in ~/.config/rgm/config.toml.
+
+`rgm kill` sends a signal to processes using a monitored GPU: TERM (the
+default), KILL, STOP or CONT.
+
+Options:
+      --config <FILE>      Read this config file instead of the default one
+      --backend <LIST>     Comma-separated backends to use: nvml, amdgpu, intel
+                           [default: all]
+      --device <LIST>      Comma-separated devices to monitor, by bus id, UUID
+                           or part of the name [default: all]
+      --headless           Print samples to stdout instead of opening a window
+      --exporter           Serve Prometheus metrics over HTTP instead of opening a window
+      --tui                Show a terminal UI instead of opening a window
+      --listen <ADDR>      Exporter listen address [default: 127.0.0.1:9835]
+  -i, --interval <TIME>    Time between samples, e.g. 500ms, 1s
+                           [default: 1s; GUI: the saved setting, 50ms to 60s]
+      --history <TIME>     Time span shown by the plots, e.g. 10m
+                           [default: the saved setting, 10s to 24h; TUI: 5m]
+  -c, --count <N>          Stop after N samples (headless only) [default: unlimited]
+  -f, --format <FORMAT>    Headless output: text or ndjson [default: text]
+      --record <FILE>      Save every sample to a recording file
+      --replay <FILE>      Play back a recording instead of monitoring live GPUs
+      --speed <FACTOR>     Replay speed, e.g. 10 for ten times faster [default: 1]
+      --alerts <FILE>      Evaluate the alert rules in a JSON file
+      --csv <FILE>         Log every sample to a CSV file
+      --csv-columns <LIST> Comma-separated CSV columns [default: all]
+      --csv-rotate-size <SIZE>
+                           Start a new CSV file after SIZE bytes, e.g. 10M
+      --csv-rotate-interval <TIME>
+                           Start a new CSV file after TIME, e.g. 1h
+  -s, --signal <SIGNAL>    Signal sent by `rgm kill` [default: TERM]
+      --log-level <LEVEL>  Messages printed on stderr: off, error, warn, info,
+                           debug or trace [default: info]
+  -h, --help               Print this help
+  -V, --version            Print version";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CliError {
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+    #[error("option '{0}' requires a value")]
+    MissingValue(String),
+    #[error("invalid value '{value}' for '{option}': {reason}")]
+    InvalidValue {
+        option: String,
+        value: String,
+        reason: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Gui,
+    Headless,
+    Exporter,
+    Tui,
+    /// `rgm kill`: signal GPU processes
+    Kill,
+    Help,
+    Version,
+}
+
+/// How headless mode prints samples
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned columns for humans
+    #[default]
+    Text,
+    /// One JSON `SampleRecord` per line
+    Ndjson,
+}
+
+/// `--interval` and `--history` as given on the command line, without the
+/// config file's defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SamplingOverrides {
+    pub interval: Option<Duration>,
+    pub history: Option<Duration>,
+}
+
+impl SamplingOverrides {
+    /// The settings of a reloaded config file that take effect: those the
+    /// command line does not override, clamped to the supported ranges.
+    pub fn reloaded(self, sampling: &SamplingConfig) -> SamplingConfig {
+        SamplingConfig {
+            interval: sampling
+                .interval
+                .filter(|_| self.interval.is_none())
+                .map(|interval| interval.clamp(MIN_SAMPLE_INTERVAL, MAX_SAMPLE_INTERVAL)),
+            history: sampling
+                .history
+                .filter(|_| self.history.is_none())
+                .map(|history| history.clamp(MIN_HISTORY, MAX_HISTORY)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CliOptions {
+    pub mode: Mode,
+    pub config: Option<PathBuf>,
+    /// Backend names; all backends when empty
+    pub backends: Vec<String>,
+    /// Device patterns for `GpuInfo::matches`; all devices when empty
+    pub devices: Vec<String>,
+    /// `None` unless given; see `DEFAULT_INTERVAL` and `Settings`
+    pub interval: Option<Duration>,
+    pub history: Option<Duration>,
+    /// Which of those came from the command line; they keep precedence
+    /// when the config file is reloaded
+    pub sampling_overrides: SamplingOverrides,
+    pub count: Option<u64>,
+    pub format: OutputFormat,
+    pub listen: String,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub speed: f64,
+    pub alerts: Option<PathBuf>,
+    pub csv: Option<PathBuf>,
+    pub csv_options: CsvOptions,
+    /// Signal and targets of `rgm kill`
+    pub signal: Signal,
+    pub pids: Vec<u32>,
+    pub log_level: LevelFilter,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Gui,
+            config: None,
+            backends: Vec::new(),
+            devices: Vec::new(),
+            interval: None,
+            history: None,
+            sampling_overrides: SamplingOverrides::default(),
+            count: None,
+            format: OutputFormat::default(),
+            listen: DEFAULT_LISTEN.to_string(),
+            record: None,
+            replay: None,
+            speed: 1.0,
+            alerts: None,
+            csv: None,
+            csv_options: CsvOptions::default(),
+            signal: Signal::Term,
+            pids: Vec::new(),
+            log_level: DEFAULT_LEVEL,
+        }
+    }
+}
+
+impl CliOptions {
+    /// Time between samples in headless and exporter mode.
+    pub fn interval(&self) -> Duration {
+        self.interval.unwrap_or(DEFAULT_INTERVAL)
+    }
+
+    /// Parse arguments, excluding the program name.
+    pub fn parse<I, S>(args: I) -> Result<Self, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::parse_onto(Self::default(), args)
+    }
+
+    /// Parse arguments over `options`, e.g. those from the config file.
+    pub fn parse_onto<I, S>(mut options: Self, args: I) -> Result<Self, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            // Accept both "--opt value" and "--opt=value"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError::MissingValue(flag.clone()))
+            };
+
+            match flag.as_str() {
+                "--headless" => options.mode = Mode::Headless,
+                "--exporter" => options.mode = Mode::Exporter,
+                "--tui" => options.mode = Mode::Tui,
+                "--listen" => options.listen = value()?,
+                "--config" => options.config = Some(PathBuf::from(value()?)),
+                "--backend" => {
+                    let raw = value()?;
+                    let known = BackendRegistry::default().names();
+                    let backends = split_list(&raw);
+                    if let Some(unknown) = backends
+                        .iter()
+                        .find(|name| !known.iter().any(|k| k.eq_ignore_ascii_case(name)))
+                    {
+                        return Err(CliError::InvalidValue {
+                            option: flag.clone(),
+                            value: raw.clone(),
+                            reason: format!(
+                                "unknown backend '{unknown}', expected one of {}",
+                                known.join(", ")
+                            ),
+                        });
+                    }
+                    options.backends = backends;
+                }
+                "--device" => options.devices = split_list(&value()?),
+                "--record" => options.record = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--speed" => {
+                    let raw = value()?;
+                    let speed = raw.parse::<f64>().ok().filter(|s| *s > 0.0);
+                    options.speed = speed.ok_or_else(|| CliError::InvalidValue {
+                        option: flag.clone(),
+                        value: raw,
+                        reason: "expected a number greater than zero".into(),
+                    })?;
+                }
+                "-i" | "--interval" => {
+                    let raw = value()?;
+                    let interval =
+                        parse_duration(&raw).map_err(|reason| CliError::InvalidValue {
+                            option: flag.clone(),
+                            value: raw,
+                            reason,
+                        })?;
+                    options.interval = Some(interval);
+                    options.sampling_overrides.interval = Some(interval);
+                }
+                "--history" => {
+                    let raw = value()?;
+                    let history =
+                        parse_duration(&raw).map_err(|reason| CliError::InvalidValue {
+                            option: flag.clone(),
+                            value: raw,
+                            reason,
+                        })?;
+                    options.history = Some(history);
+                    options.sampling_overrides.history = Some(history);
+                }
+                "-c" | "--count" => {
+                    let raw = value()?;
+                    let count = raw.parse::<u64>().ok().filter(|&n| n > 0);
+                    options.count = Some(count.ok_or_else(|| CliError::InvalidValue {
+                        option: flag.clone(),
+                        value: raw,
+                        reason: "expected a positive integer".into(),
+                    })?);
+                }
+                "--alerts" => options.alerts = Some(PathBuf::from(value()?)),
+                "--csv" => options.csv = Some(PathBuf::from(value()?)),
+                "--csv-columns" => {
+                    let raw = value()?;
+                    options.csv_options.columns =
+                        CsvColumn::parse_list(&raw).map_err(|reason| CliError::InvalidValue {
+                            option: flag.clone(),
+                            value: raw,
+                            reason,
+                        })?;
+                }
+                "--csv-rotate-size" => {
+                    let raw = value()?;
+                    let size = parse_size(&raw).map_err(|reason| CliError::InvalidValue {
+                        option: flag.clone(),
+                        value: raw,
+                        reason,
+                    })?;
+                    options.csv_options.rotate_size = Some(size);
+                }
+                "--csv-rotate-interval" => {
+                    let raw = value()?;
+                    let interval =
+                        parse_duration(&raw).map_err(|reason| CliError::InvalidValue {
+                            option: flag.clone(),
+                            value: raw,
+                            reason,
+                        })?;
+                    options.csv_options.rotate_interval = Some(interval);
+                }
+                "-f" | "--format" => {
+                    let raw = value()?;
+                    options.format = match raw.as_str() {
+                        "text" => OutputFormat::Text,
+                        "ndjson" | "json" => OutputFormat::Ndjson,
+                        _ => {
+                            return Err(CliError::InvalidValue {
+                                option: flag.clone(),
+                                value: raw,
+                                reason: "expected text or ndjson".into(),
+                            })
+                        }
+                    };
+                }
+                "-s" | "--signal" => {
+                    let raw = value()?;
+                    options.signal = Signal::parse(&raw).ok_or_else(|| CliError::InvalidValue {
+                        option: flag.clone(),
+                        value: raw,
+                        reason: "expected TERM, KILL, STOP or CONT".into(),
+                    })?;
+                }
+                "--log-level" => {
+                    let raw = value()?;
+                    options.log_level =
+                        logging::parse_level(&raw).map_err(|reason| CliError::InvalidValue {
+                            option: flag.clone(),
+                            value: raw,
+                            reason,
+                        })?;
+                }
+                "kill" if options.mode != Mode::Kill => options.mode = Mode::Kill,
+                pid if options.mode == Mode::Kill && !pid.starts_with('-') => {
+                    let parsed = pid.parse::<u32>().ok().filter(|&n| n > 0);
+                    options
+                        .pids
+                        .push(parsed.ok_or_else(|| CliError::InvalidValue {
+                            option: "kill".into(),
+                            value: pid.to_string(),
+                            reason: "expected a process id".into(),
+                        })?);
+                }
+                "-h" | "--help" => options.mode = Mode::Help,
+                "-V" | "--version" => options.mode = Mode::Version,
+                _ => return Err(CliError::UnknownOption(flag.clone())),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parse a duration like `250ms`, `1s`, `1.5s`, `2m` or `1h`. A bare number
+/// is taken as seconds.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| "expected a number followed by ms, s, m or h".to_string())?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("unknown unit '{unit}', use ms, s, m or h")),
+    };
+    if seconds <= 0.0 {
+        return Err("duration must be greater than zero".into());
+    }
+    Duration::try_from_secs_f64(seconds).map_err(|_| "duration is too long".to_string())
+}
+
+/// Format a duration in the notation `parse_duration` accepts, in the
+/// largest unit that keeps it at or above one, e.g. `250ms`, `1.5s`, `10m`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    let (value, unit) = if seconds < 1.0 {
+        (seconds * 1000.0, "ms")
+    } else if seconds < 60.0 {
+        (seconds, "s")
+    } else if seconds < 3600.0 {
+        (seconds / 60.0, "m")
+    } else {
+        (seconds / 3600.0, "h")
+    };
+    let value = format!("{value:.1}");
+    format!("{}{unit}", value.strip_suffix(".0").unwrap_or(&value))
+}
+
+/// Serde support for durations written in `parse_duration` notation, e.g.
+/// `"30s"`, for use with `#[serde(with = "duration_text")]`.
+pub mod duration_text {
+    use super::{format_duration, parse_duration};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_duration(&text).map_err(de::Error::custom)
+    }
+
+    /// The same for `Option<Duration>`, used with `#[serde(default)]`.
+    pub mod option {
+        use super::super::{format_duration, parse_duration};
+        use serde::{de, Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub fn serialize<S: Serializer>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match duration {
+                Some(duration) => serializer.serialize_str(&format_duration(*duration)),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            let text = String::deserialize(deserializer)?;
+            parse_duration(&text).map(Some).map_err(de::Error::custom)
+        }
+    }
+}
+
+/// Parse a byte size like `512`, `64K`, `10M` or `1G` (binary multiples).
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let (number, scale) = match text.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&text[..i], 1024),
+        Some((i, 'M' | 'm')) => (&text[..i], 1024 * 1024),
+        Some((i, 'G' | 'g')) => (&text[..i], 1024 * 1024 * 1024),
+        _ => (text, 1),
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 => n
+            .checked_mul(scale)
+            .ok_or_else(|| "size is too large".into()),
+        _ => Err("expected a size such as 512K, 10M or 1G".into()),
+    }
+}
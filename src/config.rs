@@ -0,0 +1,334 @@
+// Configuration file.
+//
+// Read from `--config <FILE>`, or else `$XDG_CONFIG_HOME/rgm/config.toml`
+// (`~/.config/rgm/config.toml`), with `config.json` accepted in its place.
+// The file provides defaults for the command-line options, which still take
+// precedence, plus alert rules and the GUI layout. `ConfigWatcher` picks up
+// edits while RGM runs.
+use crate::alerts::AlertRule;
+use crate::cli::{duration_text, format_duration, parse_size, CliOptions};
+use crate::csv_log::{CsvColumn, CsvOptions};
+use crate::monitor::BackendRegistry;
+use crate::settings::{MAX_HISTORY, MAX_SAMPLE_INTERVAL, MIN_HISTORY, MIN_SAMPLE_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("cannot read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("{path}: invalid '{field}': {message}")]
+    Invalid {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub backends: BackendConfig,
+    pub sampling: SamplingConfig,
+    pub exporter: ExporterConfig,
+    pub csv: CsvConfig,
+    pub alerts: Vec<AlertRule>,
+    pub ui: UiConfig,
+    pub log: LogConfig,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+    /// Backends to probe, e.g. `["amdgpu"]`; all of them when empty
+    pub enabled: Vec<String>,
+    /// Only monitor devices matching one of these; see `GpuInfo::matches`
+    pub devices: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SamplingConfig {
+    #[serde(with = "duration_text::option")]
+    pub interval: Option<Duration>,
+    #[serde(with = "duration_text::option")]
+    pub history: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExporterConfig {
+    pub listen: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvConfig {
+    pub path: Option<PathBuf>,
+    /// Comma-separated, as for `--csv-columns`
+    pub columns: Option<String>,
+    /// A size such as `10M`, as for `--csv-rotate-size`
+    pub rotate_size: Option<String>,
+    #[serde(with = "duration_text::option")]
+    pub rotate_interval: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// As for `--log-level`
+    pub level: Option<log::LevelFilter>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    /// Initial window width and height, in points
+    pub window_size: [f32; 2],
+    pub theme: Theme,
+    /// Height of each metric plot, in points
+    pub plot_height: f32,
+    /// Device shown first, matched like `backends.devices`
+    pub device: Option<String>,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            window_size: [1000.0, 700.0],
+            theme: Theme::Dark,
+            plot_height: 120.0,
+            device: None,
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/rgm/config.toml`, or `config.json` if only that
+    /// exists. `None` when neither `XDG_CONFIG_HOME` nor `HOME` is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        let dir = base.join("rgm");
+        let toml = dir.join("config.toml");
+        let json = dir.join("config.json");
+        Some(if !toml.exists() && json.exists() {
+            json
+        } else {
+            toml
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text, path)
+    }
+
+    /// Parse and validate a config file. Files ending in `.json` are read as
+    /// JSON, everything else as TOML.
+    pub fn parse(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(text).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(text).map_err(|e| e.to_string())
+        };
+        let config: Self = parsed.map_err(|message| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        config
+            .validate()
+            .map_err(|(field, message)| ConfigError::Invalid {
+                path: path.to_path_buf(),
+                field,
+                message,
+            })?;
+        Ok(config)
+    }
+
+    /// Check what serde cannot, as (field, problem).
+    fn validate(&self) -> Result<(), (String, String)> {
+        let known = BackendRegistry::default().names();
+        for (i, name) in self.backends.enabled.iter().enumerate() {
+            if !known.iter().any(|k| k.eq_ignore_ascii_case(name)) {
+                return Err((
+                    format!("backends.enabled[{i}]"),
+                    format!(
+                        "unknown backend '{name}', expected one of {}",
+                        known.join(", ")
+                    ),
+                ));
+            }
+        }
+        let ranges = [
+            (
+                "sampling.interval",
+                self.sampling.interval,
+                MIN_SAMPLE_INTERVAL..=MAX_SAMPLE_INTERVAL,
+            ),
+            (
+                "sampling.history",
+                self.sampling.history,
+                MIN_HISTORY..=MAX_HISTORY,
+            ),
+        ];
+        for (field, value, range) in ranges {
+            if value.is_some_and(|value| !range.contains(&value)) {
+                return Err((
+                    field.into(),
+                    format!(
+                        "must be between {} and {}",
+                        format_duration(*range.start()),
+                        format_duration(*range.end())
+                    ),
+                ));
+            }
+        }
+        if let Some(columns) = &self.csv.columns {
+            CsvColumn::parse_list(columns).map_err(|e| ("csv.columns".to_string(), e))?;
+        }
+        if let Some(size) = &self.csv.rotate_size {
+            parse_size(size).map_err(|e| ("csv.rotate_size".to_string(), e))?;
+        }
+
+        let mut names = HashSet::new();
+        for (i, rule) in self.alerts.iter().enumerate() {
+            let field = |name: &str| format!("alerts[{i}].{name}");
+            if !names.insert(rule.name.as_str()) {
+                return Err((field("name"), format!("duplicate rule '{}'", rule.name)));
+            }
+            if !rule.threshold.is_finite() {
+                return Err((field("threshold"), "must be a number".into()));
+            }
+            if !(0.0..).contains(&rule.hysteresis) {
+                return Err((field("hysteresis"), "must not be negative".into()));
+            }
+        }
+
+        let [width, height] = self.ui.window_size;
+        if !(200.0..).contains(&width) || !(150.0..).contains(&height) {
+            return Err((
+                "ui.window_size".into(),
+                "must be at least [200, 150]".into(),
+            ));
+        }
+        if !(40.0..=600.0).contains(&self.ui.plot_height) {
+            return Err(("ui.plot_height".into(), "must be between 40 and 600".into()));
+        }
+        Ok(())
+    }
+
+    /// Options as if the file's settings had been given on the command line,
+    /// for `CliOptions::parse_onto` to override.
+    pub fn cli_defaults(&self) -> CliOptions {
+        let defaults = CliOptions::default();
+        CliOptions {
+            interval: self.sampling.interval,
+            history: self.sampling.history,
+            listen: self.exporter.listen.clone().unwrap_or(defaults.listen),
+            csv: self.csv.path.clone(),
+            csv_options: CsvOptions {
+                columns: self
+                    .csv
+                    .columns
+                    .as_deref()
+                    .and_then(|columns| CsvColumn::parse_list(columns).ok())
+                    .unwrap_or(defaults.csv_options.columns),
+                rotate_size: self
+                    .csv
+                    .rotate_size
+                    .as_deref()
+                    .and_then(|size| parse_size(size).ok()),
+                rotate_interval: self.csv.rotate_interval,
+            },
+            backends: self.backends.enabled.clone(),
+            devices: self.backends.devices.clone(),
+            log_level: self.log.level.unwrap_or(defaults.log_level),
+            ..defaults
+        }
+    }
+}
+
+// ── Live reload ─────────────────────────────────────────────────────────────
+
+/// The current configuration, re-read whenever its file changes.
+pub struct ConfigWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    config: Config,
+}
+
+impl ConfigWatcher {
+    /// Load `explicit`, which must exist, or else the default file if there
+    /// is one. The default file is watched even when missing, so creating it
+    /// later takes effect too.
+    pub fn load(explicit: Option<PathBuf>) -> Result<Self, ConfigError> {
+        let required = explicit.is_some();
+        let path = explicit.or_else(Config::default_path);
+        let mut watcher = Self {
+            modified: path.as_deref().and_then(modified_time),
+            path,
+            config: Config::default(),
+        };
+        if let Some(path) = &watcher.path {
+            if required || watcher.modified.is_some() {
+                watcher.config = Config::load(path)?;
+            }
+        }
+        Ok(watcher)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Re-read the file if it changed since the last call. Returns the new
+    /// configuration, or why it was rejected, in which case the previous
+    /// one stays in effect. A deleted file also leaves it in effect.
+    pub fn poll(&mut self) -> Option<Result<&Config, ConfigError>> {
+        let path = self.path.as_deref()?;
+        let modified = modified_time(path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        // The file was removed; keep the last config until it reappears
+        modified?;
+        match Config::load(path) {
+            Ok(config) => {
+                self.config = config;
+                Some(Ok(&self.config))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
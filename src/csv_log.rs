@@ -0,0 +1,255 @@
+// CSV logging of samples, with selectable columns and optional rotation
+use crate::data::{GpuData, GpuInfo};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvColumn {
+    /// Wall-clock time, RFC 3339 in UTC
+    Timestamp,
+    /// Wall-clock time, seconds since the Unix epoch
+    UnixTime,
+    DeviceId,
+    Name,
+    Vendor,
+    Utilization,
+    MemoryUsed,
+    MemoryTotal,
+    Temperature,
+    GpuClock,
+    MemoryClock,
+    PowerUsage,
+    PowerLimit,
+    FanSpeed,
+    PcieTx,
+    PcieRx,
+}
+
+impl CsvColumn {
+    pub const ALL: [CsvColumn; 16] = [
+        CsvColumn::Timestamp,
+        CsvColumn::UnixTime,
+        CsvColumn::DeviceId,
+        CsvColumn::Name,
+        CsvColumn::Vendor,
+        CsvColumn::Utilization,
+        CsvColumn::MemoryUsed,
+        CsvColumn::MemoryTotal,
+        CsvColumn::Temperature,
+        CsvColumn::GpuClock,
+        CsvColumn::MemoryClock,
+        CsvColumn::PowerUsage,
+        CsvColumn::PowerLimit,
+        CsvColumn::FanSpeed,
+        CsvColumn::PcieTx,
+        CsvColumn::PcieRx,
+    ];
+
+    /// Header name, also accepted by `--csv-columns`
+    pub fn name(self) -> &'static str {
+        match self {
+            CsvColumn::Timestamp => "timestamp",
+            CsvColumn::UnixTime => "unix_time",
+            CsvColumn::DeviceId => "device_id",
+            CsvColumn::Name => "name",
+            CsvColumn::Vendor => "vendor",
+            CsvColumn::Utilization => "utilization_percent",
+            CsvColumn::MemoryUsed => "memory_used_gib",
+            CsvColumn::MemoryTotal => "memory_total_gib",
+            CsvColumn::Temperature => "temperature_c",
+            CsvColumn::GpuClock => "gpu_clock_mhz",
+            CsvColumn::MemoryClock => "memory_clock_mhz",
+            CsvColumn::PowerUsage => "power_usage_w",
+            CsvColumn::PowerLimit => "power_limit_w",
+            CsvColumn::FanSpeed => "fan_speed_percent",
+            CsvColumn::PcieTx => "pcie_tx_mb_s",
+            CsvColumn::PcieRx => "pcie_rx_mb_s",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Parse a comma-separated column list such as `timestamp,name,utilization_percent`.
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| Self::from_name(name).ok_or_else(|| format!("unknown column '{name}'")))
+            .collect()
+    }
+
+    fn value(self, unix_time: f64, info: &GpuInfo, data: &GpuData) -> String {
+        match self {
+            CsvColumn::Timestamp => format_rfc3339(unix_time),
+            CsvColumn::UnixTime => format!("{unix_time:.3}"),
+            CsvColumn::DeviceId => escape_field(&data.device_id),
+            CsvColumn::Name => escape_field(&info.name),
+            CsvColumn::Vendor => info.vendor.to_string(),
+            // Unavailable metrics are written as empty cells
+            CsvColumn::Utilization => optional(data.utilization, |v| v.to_string()),
+            CsvColumn::MemoryUsed => optional(data.memory_used, |v| format!("{v:.3}")),
+            CsvColumn::MemoryTotal => optional(data.memory_total, |v| format!("{v:.3}")),
+            CsvColumn::Temperature => optional(data.temperature, |v| v.to_string()),
+            CsvColumn::GpuClock => optional(data.gpu_clock, |v| v.to_string()),
+            CsvColumn::MemoryClock => optional(data.memory_clock, |v| v.to_string()),
+            CsvColumn::PowerUsage => optional(data.power_usage, |v| format!("{v:.2}")),
+            CsvColumn::PowerLimit => optional(data.power_limit, |v| format!("{v:.2}")),
+            CsvColumn::FanSpeed => optional(data.fan_speed, |v| v.to_string()),
+            CsvColumn::PcieTx => optional(data.pcie_throughput_tx, |v| format!("{v:.2}")),
+            CsvColumn::PcieRx => optional(data.pcie_throughput_rx, |v| format!("{v:.2}")),
+        }
+    }
+}
+
+fn optional<T>(value: Option<T>, format: impl Fn(T) -> String) -> String {
+    value.map(format).unwrap_or_default()
+}
+
+/// Quote a field if it contains a separator, quote or newline.
+fn escape_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+pub fn format_rfc3339(unix_time: f64) -> String {
+    let millis = (unix_time * 1000.0).round() as i64;
+    let secs = millis.div_euclid(1000);
+    let days = secs.div_euclid(86_400);
+    let time_of_day = secs.rem_euclid(86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60,
+        millis.rem_euclid(1000)
+    )
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    pub columns: Vec<CsvColumn>,
+    /// Start a new file once the current one reaches this many bytes
+    pub rotate_size: Option<u64>,
+    /// Start a new file once the current one has been open this long
+    pub rotate_interval: Option<Duration>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            columns: CsvColumn::ALL.to_vec(),
+            rotate_size: None,
+            rotate_interval: None,
+        }
+    }
+}
+
+/// Writes one row per sample. With rotation enabled, `gpu.csv` is followed
+/// by `gpu.1.csv`, `gpu.2.csv`, ..., each starting with a header row.
+pub struct CsvWriter {
+    base_path: PathBuf,
+    options: CsvOptions,
+    writer: BufWriter<File>,
+    path: PathBuf,
+    bytes_written: u64,
+    opened_at: Instant,
+    sequence: u32,
+}
+
+impl CsvWriter {
+    pub fn create(base_path: &Path, options: CsvOptions) -> io::Result<Self> {
+        let path = base_path.to_path_buf();
+        let mut writer = Self {
+            base_path: path.clone(),
+            options,
+            writer: BufWriter::new(File::create(&path)?),
+            path,
+            bytes_written: 0,
+            opened_at: Instant::now(),
+            sequence: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    /// The file currently being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let header: Vec<_> = self.options.columns.iter().map(|c| c.name()).collect();
+        self.write_line(&header.join(","))
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{line}")?;
+        self.writer.flush()?;
+        self.bytes_written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotation_due(&self) -> bool {
+        self.options
+            .rotate_size
+            .is_some_and(|limit| self.bytes_written >= limit)
+            || self
+                .options
+                .rotate_interval
+                .is_some_and(|interval| self.opened_at.elapsed() >= interval)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.sequence += 1;
+        let stem = self
+            .base_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file_name = match self.base_path.extension() {
+            Some(ext) => format!("{stem}.{}.{}", self.sequence, ext.to_string_lossy()),
+            None => format!("{stem}.{}", self.sequence),
+        };
+        self.path = self.base_path.with_file_name(file_name);
+        self.writer = BufWriter::new(File::create(&self.path)?);
+        self.bytes_written = 0;
+        self.opened_at = Instant::now();
+        self.write_header()
+    }
+
+    pub fn write(&mut self, info: &GpuInfo, data: &GpuData) -> io::Result<()> {
+        if self.rotation_due() {
+            self.rotate()?;
+        }
+        let unix_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        let row: Vec<_> = self
+            .options
+            .columns
+            .iter()
+            .map(|c| c.value(unix_time, info, data))
+            .collect();
+        self.write_line(&row.join(","))
+    }
+}
@@ -1,37 +1,311 @@
-// GPU data structure, storing dynamic information
-#[derive(Clone, Debug)]
+// Data structures shared by the backends, the UI and the JSON output.
+//
+// These types are serialized as-is by `rgm --headless --format ndjson`; see
+// "JSON output" in the README. Field names and units are part of that schema:
+// renaming or re-scaling a field requires bumping `SCHEMA_VERSION`.
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON schema emitted for `SampleRecord`.
+/// 2: metrics the device does not provide are `null` instead of `0`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// A dynamic GPU metric, i.e. one of the optional fields of `GpuData`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Utilization,
+    MemoryUsed,
+    MemoryTotal,
+    Temperature,
+    GpuClock,
+    MemoryClock,
+    PowerUsage,
+    PowerLimit,
+    FanSpeed,
+    PcieThroughputTx,
+    PcieThroughputRx,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 11] = [
+        Metric::Utilization,
+        Metric::MemoryUsed,
+        Metric::MemoryTotal,
+        Metric::Temperature,
+        Metric::GpuClock,
+        Metric::MemoryClock,
+        Metric::PowerUsage,
+        Metric::PowerLimit,
+        Metric::FanSpeed,
+        Metric::PcieThroughputTx,
+        Metric::PcieThroughputRx,
+    ];
+}
+
+impl std::fmt::Display for Metric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Metric::Utilization => "utilization",
+            Metric::MemoryUsed => "memory used",
+            Metric::MemoryTotal => "memory total",
+            Metric::Temperature => "temperature",
+            Metric::GpuClock => "GPU clock",
+            Metric::MemoryClock => "memory clock",
+            Metric::PowerUsage => "power usage",
+            Metric::PowerLimit => "power limit",
+            Metric::FanSpeed => "fan speed",
+            Metric::PcieThroughputTx => "PCIe TX",
+            Metric::PcieThroughputRx => "PCIe RX",
+        })
+    }
+}
+
+/// A metric the device supports but that could not be read this sample.
+/// Metrics the device does not support at all are simply `None`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetricError {
+    pub metric: Metric,
+    pub message: String,
+}
+
+// GPU data structure, storing dynamic information. `None` means the value is
+// unavailable: either unsupported by the device, or listed in `errors`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GpuData {
+    /// Identity of the device this sample was taken from (matches `GpuInfo::bus_id`)
+    pub device_id: String,
+    /// Seconds since the monitor was created
     pub timestamp: f64,
-    pub utilization: f32,
-    pub memory_used: f64,
-    pub memory_total: f64,
-    pub temperature: u32,
-    pub gpu_clock: u32,
-    pub memory_clock: u32,
-    pub power_usage: f64,
-    pub power_limit: f64,
-    pub fan_speed: u32,
-    pub pcie_throughput_tx: f64,
-    pub pcie_throughput_rx: f64,
+    /// Percent
+    pub utilization: Option<f32>,
+    /// GiB
+    pub memory_used: Option<f64>,
+    /// GiB
+    pub memory_total: Option<f64>,
+    /// °C
+    pub temperature: Option<u32>,
+    /// MHz
+    pub gpu_clock: Option<u32>,
+    /// MHz
+    pub memory_clock: Option<u32>,
+    /// W
+    pub power_usage: Option<f64>,
+    /// W
+    pub power_limit: Option<f64>,
+    /// Percent
+    pub fan_speed: Option<u32>,
+    /// MB/s
+    pub pcie_throughput_tx: Option<f64>,
+    /// MB/s
+    pub pcie_throughput_rx: Option<f64>,
+    /// ms the backend took to collect this sample, process list included
+    #[serde(default)]
+    pub collection_time: Option<f64>,
+    /// Metrics that failed to read this sample
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<MetricError>,
+}
+
+impl GpuData {
+    /// A sample with every metric unavailable.
+    pub fn empty(device_id: impl Into<String>, timestamp: f64) -> Self {
+        Self {
+            device_id: device_id.into(),
+            timestamp,
+            utilization: None,
+            memory_used: None,
+            memory_total: None,
+            temperature: None,
+            gpu_clock: None,
+            memory_clock: None,
+            power_usage: None,
+            power_limit: None,
+            fan_speed: None,
+            pcie_throughput_tx: None,
+            pcie_throughput_rx: None,
+            collection_time: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// The value of `metric` as a float, if available.
+    pub fn metric(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Utilization => self.utilization.map(f64::from),
+            Metric::MemoryUsed => self.memory_used,
+            Metric::MemoryTotal => self.memory_total,
+            Metric::Temperature => self.temperature.map(f64::from),
+            Metric::GpuClock => self.gpu_clock.map(f64::from),
+            Metric::MemoryClock => self.memory_clock.map(f64::from),
+            Metric::PowerUsage => self.power_usage,
+            Metric::PowerLimit => self.power_limit,
+            Metric::FanSpeed => self.fan_speed.map(f64::from),
+            Metric::PcieThroughputTx => self.pcie_throughput_tx,
+            Metric::PcieThroughputRx => self.pcie_throughput_rx,
+        }
+    }
+
+    /// Set `metric` from a float, rounding integer fields.
+    pub fn set_metric(&mut self, metric: Metric, value: Option<f64>) {
+        let whole = value.map(|v| v.round().max(0.0) as u32);
+        match metric {
+            Metric::Utilization => self.utilization = value.map(|v| v as f32),
+            Metric::MemoryUsed => self.memory_used = value,
+            Metric::MemoryTotal => self.memory_total = value,
+            Metric::Temperature => self.temperature = whole,
+            Metric::GpuClock => self.gpu_clock = whole,
+            Metric::MemoryClock => self.memory_clock = whole,
+            Metric::PowerUsage => self.power_usage = value,
+            Metric::PowerLimit => self.power_limit = value,
+            Metric::FanSpeed => self.fan_speed = whole,
+            Metric::PcieThroughputTx => self.pcie_throughput_tx = value,
+            Metric::PcieThroughputRx => self.pcie_throughput_rx = value,
+        }
+    }
+
+    /// The read error for `metric` this sample, if there was one.
+    pub fn error(&self, metric: Metric) -> Option<&MetricError> {
+        self.errors.iter().find(|e| e.metric == metric)
+    }
+}
+
+// GPU vendor, used to label devices on mixed-vendor systems
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    #[default]
+    Unknown,
+}
+
+impl std::fmt::Display for GpuVendor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GpuVendor::Nvidia => "NVIDIA",
+            GpuVendor::Amd => "AMD",
+            GpuVendor::Intel => "Intel",
+            GpuVendor::Unknown => "Unknown",
+        })
+    }
 }
 
 // GPU information structure, storing static information
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct GpuInfo {
+    pub vendor: GpuVendor,
     pub name: String,
     pub uuid: String,
+    /// PCI bus address, e.g. `0000:03:00.0`; stable across vendors
+    pub bus_id: String,
     pub pcie_gen: u32,
     pub pcie_width: u32,
     pub driver_version: String,
     pub vbios_version: String,
+    /// Highest GPU clock the driver allows, in MHz, where known
+    #[serde(default)]
+    pub max_gpu_clock: Option<u32>,
+}
+
+impl GpuInfo {
+    /// Whether a `--device` pattern selects this device: its bus id or UUID,
+    /// or part of its name, ignoring case.
+    pub fn matches(&self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        self.bus_id.eq_ignore_ascii_case(pattern)
+            || self.uuid.eq_ignore_ascii_case(pattern)
+            || self.name.to_lowercase().contains(&pattern.to_lowercase())
+    }
+}
+
+/// What a process uses the GPU for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessType {
+    Graphics,
+    Compute,
+    /// Both graphics and compute contexts
+    GraphicsCompute,
+}
+
+impl ProcessType {
+    /// The type of a process seen with both `self` and `other` contexts.
+    pub fn merge(self, other: ProcessType) -> ProcessType {
+        if self == other {
+            self
+        } else {
+            ProcessType::GraphicsCompute
+        }
+    }
+
+    /// Short label as printed by `nvidia-smi`: `G`, `C` or `C+G`.
+    pub fn label(self) -> &'static str {
+        match self {
+            ProcessType::Graphics => "G",
+            ProcessType::Compute => "C",
+            ProcessType::GraphicsCompute => "C+G",
+        }
+    }
 }
 
 // Process information structure, storing information about GPU processes
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
+    /// Owner of the process; its numeric uid if it has no passwd entry
+    #[serde(default)]
+    pub user: String,
+    /// Arguments joined by spaces; empty for kernel threads and processes
+    /// that are not readable
+    #[serde(default)]
+    pub command_line: String,
+    /// Bytes of GPU memory held by the process
     pub memory_usage: u64,
-    #[allow(dead_code)]
+    /// Resident host memory, in bytes
+    #[serde(default)]
+    pub host_memory: u64,
+    /// Share of one CPU used since the previous sample, in percent
     pub cpu_percent: f32,
+    /// Share of GPU engine time used since the previous sample, in percent
+    pub gpu_utilization: f32,
+    /// `None` when the driver does not tell
+    #[serde(default)]
+    pub process_type: Option<ProcessType>,
+    /// Streaming multiprocessor, encoder and decoder utilization in percent,
+    /// where the driver reports them per process
+    #[serde(default)]
+    pub sm_utilization: Option<f32>,
+    #[serde(default)]
+    pub encoder_utilization: Option<f32>,
+    #[serde(default)]
+    pub decoder_utilization: Option<f32>,
+}
+
+/// One line of NDJSON output: a sample together with the device it came from
+/// and the processes running on it at that moment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SampleRecord {
+    pub schema_version: u32,
+    /// Wall-clock time of the sample, seconds since the Unix epoch
+    pub unix_time: f64,
+    pub device: GpuInfo,
+    pub sample: GpuData,
+    pub processes: Vec<ProcessInfo>,
+}
+
+impl SampleRecord {
+    pub fn new(device: GpuInfo, sample: GpuData, processes: Vec<ProcessInfo>) -> Self {
+        let unix_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        Self {
+            schema_version: SCHEMA_VERSION,
+            unix_time,
+            device,
+            sample,
+            processes,
+        }
+    }
 }
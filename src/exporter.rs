@@ -0,0 +1,283 @@
+// Prometheus exporter: serves the latest sample of every GPU on `/metrics`
+use crate::alerts::{configured_rules, AlertEngine};
+use crate::cli::CliOptions;
+use crate::config::ConfigWatcher;
+use crate::data::{GpuData, GpuInfo, Metric, ProcessInfo};
+use crate::recording::load_supervised;
+use crate::sampler::DeviceSet;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:9835";
+
+const BYTES_PER_GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+const BYTES_PER_MIB: f64 = 1024.0 * 1024.0;
+
+/// Latest state of one device, as exposed on `/metrics`.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceSnapshot {
+    pub info: GpuInfo,
+    /// `None` until the first successful sample, and while sampling fails
+    pub data: Option<GpuData>,
+    pub processes: Vec<ProcessInfo>,
+}
+
+/// (name, help, value) for every per-GPU gauge. Devices that do not provide
+/// a metric get no series for it rather than a zero.
+type GaugeDef = (&'static str, &'static str, fn(&GpuData) -> Option<f64>);
+
+const GPU_GAUGES: &[GaugeDef] = &[
+    ("rgm_gpu_utilization_percent", "GPU utilization", |d| {
+        d.metric(Metric::Utilization)
+    }),
+    ("rgm_gpu_memory_used_bytes", "GPU memory in use", |d| {
+        d.memory_used.map(|v| v * BYTES_PER_GIB)
+    }),
+    ("rgm_gpu_memory_total_bytes", "Total GPU memory", |d| {
+        d.memory_total.map(|v| v * BYTES_PER_GIB)
+    }),
+    ("rgm_gpu_temperature_celsius", "GPU temperature", |d| {
+        d.metric(Metric::Temperature)
+    }),
+    ("rgm_gpu_clock_mhz", "Graphics clock", |d| {
+        d.metric(Metric::GpuClock)
+    }),
+    ("rgm_gpu_memory_clock_mhz", "Memory clock", |d| {
+        d.metric(Metric::MemoryClock)
+    }),
+    ("rgm_gpu_power_usage_watts", "Power draw", |d| d.power_usage),
+    ("rgm_gpu_power_limit_watts", "Power limit", |d| {
+        d.power_limit
+    }),
+    ("rgm_gpu_fan_speed_percent", "Fan speed", |d| {
+        d.metric(Metric::FanSpeed)
+    }),
+    (
+        "rgm_gpu_pcie_tx_bytes_per_second",
+        "PCIe transmit throughput",
+        |d| d.pcie_throughput_tx.map(|v| v * BYTES_PER_MIB),
+    ),
+    (
+        "rgm_gpu_pcie_rx_bytes_per_second",
+        "PCIe receive throughput",
+        |d| d.pcie_throughput_rx.map(|v| v * BYTES_PER_MIB),
+    ),
+    (
+        "rgm_sample_collection_seconds",
+        "Time taken to collect the sample",
+        |d| d.collection_time.map(|ms| ms / 1000.0),
+    ),
+];
+
+/// Escape a label value per the Prometheus text exposition format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn device_labels(info: &GpuInfo) -> String {
+    format!(
+        "uuid=\"{}\",name=\"{}\",vendor=\"{}\",bus_id=\"{}\"",
+        escape_label(&info.uuid),
+        escape_label(&info.name),
+        info.vendor.to_string().to_lowercase(),
+        escape_label(&info.bus_id),
+    )
+}
+
+/// Render every device in the Prometheus text exposition format.
+pub fn render_metrics(devices: &[DeviceSnapshot]) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP rgm_gpu_info Static GPU information");
+    let _ = writeln!(out, "# TYPE rgm_gpu_info gauge");
+    for device in devices {
+        let _ = writeln!(
+            out,
+            "rgm_gpu_info{{{},driver_version=\"{}\",vbios_version=\"{}\"}} 1",
+            device_labels(&device.info),
+            escape_label(&device.info.driver_version),
+            escape_label(&device.info.vbios_version),
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP rgm_gpu_up Whether the latest sample of the GPU succeeded"
+    );
+    let _ = writeln!(out, "# TYPE rgm_gpu_up gauge");
+    for device in devices {
+        let up = u8::from(device.data.is_some());
+        let _ = writeln!(out, "rgm_gpu_up{{{}}} {up}", device_labels(&device.info));
+    }
+
+    for (name, help, value) in GPU_GAUGES {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        for device in devices {
+            if let Some(reading) = device.data.as_ref().and_then(value) {
+                let _ = writeln!(out, "{name}{{{}}} {}", device_labels(&device.info), reading);
+            }
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP rgm_process_gpu_memory_bytes GPU memory held by a process"
+    );
+    let _ = writeln!(out, "# TYPE rgm_process_gpu_memory_bytes gauge");
+    for device in devices {
+        for process in &device.processes {
+            let _ = writeln!(
+                out,
+                "rgm_process_gpu_memory_bytes{{{},pid=\"{}\",process=\"{}\"}} {}",
+                device_labels(&device.info),
+                process.pid,
+                escape_label(&process.name),
+                process.memory_usage
+            );
+        }
+    }
+
+    out
+}
+
+/// The path of an HTTP request's target, without the query string.
+pub fn request_path(request: &str) -> &str {
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+    target.split_once('?').map_or(target, |(path, _)| path)
+}
+
+/// Sample every GPU in the background and serve `/metrics` until killed.
+pub fn run(options: &CliOptions, mut config: ConfigWatcher) -> Result<(), String> {
+    let (report, probe, rescan) = load_supervised(options);
+    for failure in &report.failures {
+        log::warn!("{}: {}", failure.backend, failure.error);
+    }
+    if report.monitors.is_empty() {
+        return Err("no compatible GPU found".into());
+    }
+
+    let mut devices = DeviceSet::new(report.monitors);
+    devices.supervise(probe, rescan);
+    let snapshot = |info: &GpuInfo| DeviceSnapshot {
+        info: info.clone(),
+        ..Default::default()
+    };
+    let snapshots: Arc<Mutex<Vec<DeviceSnapshot>>> = Arc::new(Mutex::new(
+        devices
+            .devices
+            .iter()
+            .map(|(info, _)| snapshot(info))
+            .collect(),
+    ));
+
+    let mut alerts = AlertEngine::new(configured_rules(
+        config.config(),
+        options.alerts.as_deref(),
+    )?);
+    let alerts_file = options.alerts.clone();
+    let sampler_snapshots = Arc::clone(&snapshots);
+    let mut interval = options.interval();
+    let overrides = options.sampling_overrides;
+    std::thread::spawn(move || {
+        let mut next = Instant::now();
+        loop {
+            if let Some(sampling) = alerts.reload(&mut config, alerts_file.as_deref()) {
+                interval = overrides.reloaded(&sampling).interval.unwrap_or(interval);
+            }
+            let known = devices.devices.len();
+            devices.reconnect();
+            sampler_snapshots.lock().unwrap().extend(
+                devices.devices[known..]
+                    .iter()
+                    .map(|(info, _)| snapshot(info)),
+            );
+
+            for (index, (info, link)) in devices.devices.iter_mut().enumerate() {
+                let was_offline = link.is_offline();
+                match link.sample() {
+                    Some(Ok(mut batch)) => {
+                        if was_offline {
+                            log::info!("gpu {index} is back online");
+                        }
+                        if let Some((data, processes)) = batch.pop() {
+                            alerts.process(info, &data);
+                            let mut snapshots = sampler_snapshots.lock().unwrap();
+                            snapshots[index].data = Some(data);
+                            snapshots[index].processes = processes;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        if link.is_offline() {
+                            log::warn!("gpu {index} is offline: {e}");
+                        } else {
+                            log::warn!("gpu {index}: {e}");
+                        }
+                        // Stale gauges would hide that the GPU is gone
+                        let mut snapshots = sampler_snapshots.lock().unwrap();
+                        snapshots[index].data = None;
+                        snapshots[index].processes.clear();
+                    }
+                    // Waiting to be reconnected
+                    None => {}
+                }
+            }
+            next += interval;
+            std::thread::sleep(next.saturating_duration_since(Instant::now()));
+        }
+    });
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(serve(&options.listen, snapshots))
+}
+
+async fn serve(listen: &str, snapshots: Arc<Mutex<Vec<DeviceSnapshot>>>) -> Result<(), String> {
+    let listener = TcpListener::bind(listen)
+        .await
+        .map_err(|e| format!("cannot listen on {listen}: {e}"))?;
+    log::info!("serving metrics on http://{listen}/metrics");
+
+    loop {
+        let (mut stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::warn!("accept failed: {e}");
+                continue;
+            }
+        };
+        let snapshots = Arc::clone(&snapshots);
+        tokio::spawn(async move {
+            // Only the request line matters; scrapers send small requests
+            let mut buf = [0u8; 1024];
+            let Ok(n) = stream.read(&mut buf).await else {
+                return;
+            };
+            let request = String::from_utf8_lossy(&buf[..n]);
+
+            let (status, content_type, body) = match request_path(&request) {
+                "/metrics" => {
+                    let body = render_metrics(&snapshots.lock().unwrap());
+                    ("200 OK", "text/plain; version=0.0.4", body)
+                }
+                "/" => (
+                    "200 OK",
+                    "text/html",
+                    "<html><body><a href=\"/metrics\">Metrics</a></body></html>".to_string(),
+                ),
+                _ => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+            };
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
@@ -0,0 +1,401 @@
+// Per-process GPU usage from DRM fdinfo (`/proc/<pid>/fdinfo/<fd>`).
+//
+// Kernels 5.19+ expose `drm-*` keys for every open DRM file descriptor:
+//
+//   drm-driver:     amdgpu
+//   drm-pdev:       0000:03:00.0
+//   drm-client-id:  42
+//   drm-engine-gfx: 123456789 ns
+//   drm-memory-vram: 1048576 KiB
+//
+// The keys are driver-agnostic, so one scanner serves amdgpu, i915, xe and
+// any other DRM driver that implements them. xe reports busy GPU cycles
+// against the GPU's total cycles instead of engine time:
+//
+//   drm-cycles-rcs:       28257900
+//   drm-total-cycles-rcs: 7655183225
+use crate::data::{ProcessInfo, ProcessType};
+use crate::monitor::{read_process_name, PROCFS_ROOT};
+use crate::processes::HostProcesses;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+/// Usage reported by one DRM client (one open device context).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrmClient {
+    pub driver: String,
+    pub pdev: String,
+    pub client_id: u64,
+    /// Cumulative busy time per engine, in nanoseconds
+    pub engine_ns: HashMap<String, u64>,
+    /// Cumulative (busy, total) GPU cycles per engine, reported by xe
+    pub engine_cycles: HashMap<String, (u64, u64)>,
+    /// Memory per region (vram, gtt, local0, system0, ...), in bytes
+    pub memory: HashMap<String, u64>,
+}
+
+impl DrmClient {
+    /// Total busy time across all engines, in nanoseconds.
+    pub fn total_engine_ns(&self) -> u64 {
+        self.engine_ns.values().sum()
+    }
+
+    /// Graphics and/or compute, by which kinds of engine the client has
+    /// used; `None` if it has not been busy yet.
+    pub fn process_type(&self) -> Option<ProcessType> {
+        let busy_ns = self.engine_ns.iter().filter(|(_, ns)| **ns > 0);
+        let busy_cycles = self.engine_cycles.iter().filter(|(_, (busy, _))| *busy > 0);
+        busy_ns
+            .map(|(engine, _)| engine)
+            .chain(busy_cycles.map(|(engine, _)| engine))
+            .filter_map(|engine| match engine.as_str() {
+                "gfx" | "render" | "rcs" => Some(ProcessType::Graphics),
+                "compute" | "ccs" => Some(ProcessType::Compute),
+                _ => None,
+            })
+            .reduce(ProcessType::merge)
+    }
+
+    /// Bytes held in device-local memory (`vram`, `local*`). Falls back to
+    /// every region for integrated GPUs, which have no local memory.
+    pub fn device_memory(&self) -> u64 {
+        let local: u64 = self
+            .memory
+            .iter()
+            .filter(|(region, _)| region.starts_with("vram") || region.starts_with("local"))
+            .map(|(_, bytes)| bytes)
+            .sum();
+        if local > 0 {
+            local
+        } else {
+            self.memory.values().sum()
+        }
+    }
+}
+
+/// Parse the contents of a fdinfo file. Returns `None` for non-DRM fds.
+pub fn parse_fdinfo(text: &str) -> Option<DrmClient> {
+    let mut client = DrmClient::default();
+    let mut has_client_id = false;
+    // Newer kernels report drm-resident-<region>/drm-total-<region>; older
+    // amdgpu reports drm-memory-<region>. Prefer resident when both exist.
+    let mut resident = HashMap::new();
+    let mut legacy = HashMap::new();
+    let mut total = HashMap::new();
+    let mut busy_cycles = HashMap::new();
+    let mut total_cycles = HashMap::new();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "drm-driver" => client.driver = value.to_string(),
+            "drm-pdev" => client.pdev = value.to_string(),
+            "drm-client-id" => {
+                client.client_id = value.parse().ok()?;
+                has_client_id = true;
+            }
+            key => {
+                if let Some(engine) = key.strip_prefix("drm-engine-") {
+                    // drm-engine-capacity-<engine> is a count, not a time
+                    if !engine.starts_with("capacity-") {
+                        if let Some(ns) = parse_quantity(value) {
+                            client.engine_ns.insert(engine.to_string(), ns);
+                        }
+                    }
+                } else if let Some(engine) = key.strip_prefix("drm-cycles-") {
+                    busy_cycles.extend(parse_quantity(value).map(|c| (engine.to_string(), c)));
+                } else if let Some(engine) = key.strip_prefix("drm-total-cycles-") {
+                    total_cycles.extend(parse_quantity(value).map(|c| (engine.to_string(), c)));
+                } else if let Some(region) = key.strip_prefix("drm-resident-") {
+                    resident.extend(parse_quantity(value).map(|b| (region.to_string(), b)));
+                } else if let Some(region) = key.strip_prefix("drm-memory-") {
+                    legacy.extend(parse_quantity(value).map(|b| (region.to_string(), b)));
+                } else if let Some(region) = key.strip_prefix("drm-total-") {
+                    total.extend(parse_quantity(value).map(|b| (region.to_string(), b)));
+                }
+            }
+        }
+    }
+
+    if !has_client_id {
+        return None;
+    }
+    client.memory = [resident, legacy, total]
+        .into_iter()
+        .find(|m| !m.is_empty())
+        .unwrap_or_default();
+    client.engine_cycles = busy_cycles
+        .into_iter()
+        .filter_map(|(engine, busy)| {
+            let total = total_cycles.get(&engine).copied()?;
+            Some((engine, (busy, total)))
+        })
+        .collect();
+    Some(client)
+}
+
+/// Parse `"<n>"`, `"<n> ns"` or `"<n> KiB|MiB|GiB"` into a plain integer
+/// (nanoseconds or bytes).
+fn parse_quantity(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let number: u64 = parts.next()?.parse().ok()?;
+    let scale = match parts.next() {
+        None | Some("ns") => 1,
+        Some("KiB") => 1024,
+        Some("MiB") => 1024 * 1024,
+        Some("GiB") => 1024 * 1024 * 1024,
+        Some(_) => return None,
+    };
+    number.checked_mul(scale)
+}
+
+// Counters of one DRM client at one scan, to diff against the next
+struct ClientSnapshot {
+    time: Instant,
+    engine_ns: u64,
+    engine_cycles: HashMap<String, (u64, u64)>,
+}
+
+impl ClientSnapshot {
+    fn new(client: &DrmClient, time: Instant) -> Self {
+        Self {
+            time,
+            engine_ns: client.total_engine_ns(),
+            engine_cycles: client.engine_cycles.clone(),
+        }
+    }
+
+    /// Percentage of the time between the two snapshots the client kept
+    /// engines busy, summed over engines.
+    fn busy_percent_until(&self, later: &ClientSnapshot) -> f64 {
+        let mut busy = 0.0;
+        let elapsed_ns = later.time.duration_since(self.time).as_nanos() as f64;
+        if elapsed_ns > 0.0 && later.engine_ns >= self.engine_ns {
+            busy += (later.engine_ns - self.engine_ns) as f64 / elapsed_ns;
+        }
+        for (engine, (busy_cycles, total_cycles)) in &later.engine_cycles {
+            let Some((last_busy, last_total)) = self.engine_cycles.get(engine) else {
+                continue;
+            };
+            if total_cycles > last_total && busy_cycles >= last_busy {
+                busy += (busy_cycles - last_busy) as f64 / (total_cycles - last_total) as f64;
+            }
+        }
+        busy * 100.0
+    }
+}
+
+/// Scans procfs for DRM clients of one PCI device and turns them into
+/// `ProcessInfo` entries. Engine busy time (or xe's busy cycles) is
+/// converted into a utilization percentage between consecutive scans, so
+/// the first scan reports 0%.
+pub struct FdinfoScanner {
+    procfs_root: PathBuf,
+    pdev: String,
+    source: Arc<DrmClients>,
+    /// (pid, client id) -> counters at the last scan, `None` until a scan
+    /// could read procfs
+    previous: Mutex<Option<HashMap<(u32, u64), ClientSnapshot>>>,
+    host: HostProcesses,
+}
+
+impl FdinfoScanner {
+    /// A scanner with its own walk of `procfs_root`.
+    pub fn new(procfs_root: impl Into<PathBuf>, pdev: impl Into<String>) -> Self {
+        Self::shared(&DrmClients::new(procfs_root), pdev)
+    }
+
+    /// A scanner taking its clients from `source`, which the scanners of
+    /// other devices share.
+    pub fn shared(source: &Arc<DrmClients>, pdev: impl Into<String>) -> Self {
+        Self {
+            host: HostProcesses::new(&source.procfs_root),
+            procfs_root: source.procfs_root.clone(),
+            pdev: pdev.into(),
+            source: Arc::clone(source),
+            previous: Mutex::new(None),
+        }
+    }
+
+    /// Collect every DRM client of this device, keyed by PID. File
+    /// descriptors sharing a client id (dup'd or inherited) count once.
+    pub fn clients(&self) -> HashMap<u32, Vec<DrmClient>> {
+        self.source.take(&self.pdev).unwrap_or_default()
+    }
+
+    /// Per-process VRAM and engine utilization for this device.
+    pub fn scan(&self) -> Vec<ProcessInfo> {
+        self.measure().processes
+    }
+
+    /// Like `scan`, and also how busy the processes kept the device.
+    pub fn measure(&self) -> FdinfoScan {
+        let now = Instant::now();
+        let mut previous = self.previous.lock().unwrap();
+        let Some(clients) = self.source.take(&self.pdev) else {
+            // Without procfs nothing can be measured, now or next time
+            *previous = None;
+            return FdinfoScan {
+                processes: Vec::new(),
+                busy_percent: None,
+            };
+        };
+        let mut current = HashMap::new();
+
+        let mut processes: Vec<ProcessInfo> = clients
+            .into_iter()
+            .map(|(pid, clients)| {
+                let mut busy_percent = 0.0;
+                for client in &clients {
+                    let key = (pid, client.client_id);
+                    let snapshot = ClientSnapshot::new(client, now);
+                    if let Some(last) = previous.as_ref().and_then(|p| p.get(&key)) {
+                        busy_percent += last.busy_percent_until(&snapshot);
+                    }
+                    current.insert(key, snapshot);
+                }
+
+                ProcessInfo {
+                    pid,
+                    name: read_process_name(&self.procfs_root, pid),
+                    memory_usage: clients.iter().map(DrmClient::device_memory).sum(),
+                    gpu_utilization: busy_percent.min(100.0) as f32,
+                    process_type: clients
+                        .iter()
+                        .filter_map(DrmClient::process_type)
+                        .reduce(ProcessType::merge),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        // Drop state for clients that have gone away. With a readable
+        // previous scan, no busy time means the device was idle.
+        let measured = previous.replace(current).is_some();
+        self.host.fill(&mut processes);
+        processes.sort_by_key(|p| p.pid);
+        let busy_percent = measured.then(|| {
+            processes
+                .iter()
+                .map(|p| p.gpu_utilization)
+                .sum::<f32>()
+                .min(100.0)
+        });
+        FdinfoScan {
+            processes,
+            busy_percent,
+        }
+    }
+}
+
+/// Result of `FdinfoScanner::measure`.
+pub struct FdinfoScan {
+    pub processes: Vec<ProcessInfo>,
+    /// Engine utilization of all processes together, capped at 100%.
+    /// `None` on the first scan, as no busy time could be measured yet, and
+    /// whenever procfs could not be read.
+    pub busy_percent: Option<f32>,
+}
+
+// ── Shared procfs walk ──────────────────────────────────────────────────────
+
+/// Every DRM client in procfs, for the scanners of all devices. Each device
+/// takes its share of a walk of `/proc/*/fdinfo` once; a device asking
+/// again starts a new walk. Devices sampled at the same interval thereby
+/// cost one walk per tick rather than one each.
+pub struct DrmClients {
+    procfs_root: PathBuf,
+    latest: Mutex<Option<Walk>>,
+}
+
+// Clients found by one walk, by PCI device and PID (`None` if procfs could
+// not be read), and the devices that have taken theirs
+struct Walk {
+    clients: Option<HashMap<String, HashMap<u32, Vec<DrmClient>>>>,
+    taken: HashSet<String>,
+}
+
+impl DrmClients {
+    pub fn new(procfs_root: impl Into<PathBuf>) -> Arc<Self> {
+        Arc::new(Self {
+            procfs_root: procfs_root.into(),
+            latest: Mutex::new(None),
+        })
+    }
+
+    /// The instance for `/proc`, shared by every backend.
+    pub fn host() -> Arc<Self> {
+        static HOST: OnceLock<Arc<DrmClients>> = OnceLock::new();
+        Arc::clone(HOST.get_or_init(|| DrmClients::new(PROCFS_ROOT)))
+    }
+
+    /// The clients of `pdev` by PID, from the latest walk unless `pdev`
+    /// already took its share of it. `None` if procfs could not be read.
+    fn take(&self, pdev: &str) -> Option<HashMap<u32, Vec<DrmClient>>> {
+        let mut latest = self.latest.lock().unwrap();
+        let walk = match &mut *latest {
+            Some(walk) if !walk.taken.contains(pdev) => walk,
+            stale => stale.insert(Walk {
+                clients: self.read_clients(),
+                taken: HashSet::new(),
+            }),
+        };
+        walk.taken.insert(pdev.to_string());
+        let clients = walk.clients.as_mut()?;
+        Some(clients.remove(pdev).unwrap_or_default())
+    }
+
+    /// Walk procfs for DRM clients, by PCI device and PID.
+    fn read_clients(&self) -> Option<HashMap<String, HashMap<u32, Vec<DrmClient>>>> {
+        let mut clients: HashMap<String, HashMap<u32, Vec<DrmClient>>> = HashMap::new();
+        let proc_dir = std::fs::read_dir(&self.procfs_root).ok()?;
+
+        for entry in proc_dir.filter_map(|e| e.ok()) {
+            let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            // Other users' processes are not readable; skip them silently
+            let Ok(fds) = std::fs::read_dir(entry.path().join("fdinfo")) else {
+                continue;
+            };
+
+            for fd in fds.filter_map(|e| e.ok()) {
+                if !is_drm_fd(&entry.path(), &fd.file_name()) {
+                    continue;
+                }
+                let Some(client) = std::fs::read_to_string(fd.path())
+                    .ok()
+                    .and_then(|text| parse_fdinfo(&text))
+                else {
+                    continue;
+                };
+                // File descriptors sharing a client id count once
+                let known = clients
+                    .entry(client.pdev.clone())
+                    .or_default()
+                    .entry(pid)
+                    .or_default();
+                if !known.iter().any(|c| c.client_id == client.client_id) {
+                    known.push(client);
+                }
+            }
+        }
+        Some(clients)
+    }
+}
+
+/// Whether `<proc>/<pid>/fd/<fd>` points at a DRM device node. Reading the
+/// link is much cheaper than reading fdinfo for every socket and file; if the
+/// link cannot be read, fall back to parsing the fdinfo.
+fn is_drm_fd(pid_dir: &Path, fd: &std::ffi::OsStr) -> bool {
+    match std::fs::read_link(pid_dir.join("fd").join(fd)) {
+        Ok(target) => target.starts_with("/dev/dri"),
+        Err(_) => true,
+    }
+}
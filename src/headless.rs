@@ -0,0 +1,181 @@
+// Headless mode: print one row per GPU per interval, like `nvidia-smi dmon`
+use crate::alerts::{configured_rules, AlertEngine};
+use crate::cli::{CliOptions, OutputFormat};
+use crate::config::ConfigWatcher;
+use crate::csv_log::CsvWriter;
+use crate::data::{GpuData, GpuInfo, Metric, SampleRecord};
+use crate::recording::{load_supervised, Recorder};
+use crate::sampler::DeviceSet;
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// Repeat the column header every this many samples
+const HEADER_EVERY: u64 = 20;
+
+const HEADER: &str = "\
+# gpu   util   mem_used  mem_total  temp  gpu_clk  mem_clk   power  fan  pcie_tx  pcie_rx
+# idx      %        GiB        GiB     C      MHz      MHz       W    %     MB/s     MB/s";
+
+/// Right-align a metric in `width` columns, or `-` if it is unavailable.
+fn cell(data: &GpuData, metric: Metric, width: usize, precision: usize) -> String {
+    match data.metric(metric) {
+        Some(value) => format!("{value:>width$.precision$}"),
+        None => format!("{:>width$}", "-"),
+    }
+}
+
+pub fn format_row(index: usize, data: &GpuData) -> String {
+    format!(
+        "{index:>5} {} {} {} {} {} {} {} {} {} {}",
+        cell(data, Metric::Utilization, 6, 0),
+        cell(data, Metric::MemoryUsed, 10, 2),
+        cell(data, Metric::MemoryTotal, 10, 2),
+        cell(data, Metric::Temperature, 5, 0),
+        cell(data, Metric::GpuClock, 8, 0),
+        cell(data, Metric::MemoryClock, 8, 0),
+        cell(data, Metric::PowerUsage, 7, 1),
+        cell(data, Metric::FanSpeed, 4, 0),
+        cell(data, Metric::PcieThroughputTx, 8, 2),
+        cell(data, Metric::PcieThroughputRx, 8, 2),
+    )
+}
+
+pub fn run(options: &CliOptions, mut config: ConfigWatcher) -> Result<(), String> {
+    let (report, probe, rescan) = load_supervised(options);
+    if report.monitors.is_empty() {
+        let reasons: Vec<String> = report
+            .failures
+            .iter()
+            .map(|f| format!("  {}: {}", f.backend, f.error))
+            .collect();
+        return Err(format!("no compatible GPU found\n{}", reasons.join("\n")));
+    }
+
+    let mut devices = DeviceSet::new(report.monitors);
+    devices.supervise(probe, rescan);
+    let infos: Vec<_> = devices
+        .devices
+        .iter()
+        .map(|(info, _)| info.clone())
+        .collect();
+
+    let mut alerts = AlertEngine::new(configured_rules(
+        config.config(),
+        options.alerts.as_deref(),
+    )?);
+    let mut recorder = match &options.record {
+        Some(path) => Some(
+            Recorder::create(path, infos.clone())
+                .map_err(|e| format!("cannot record to {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+    let mut csv = match &options.csv {
+        Some(path) => Some(
+            CsvWriter::create(path, options.csv_options.clone())
+                .map_err(|e| format!("cannot write {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    let mut out = io::stdout().lock();
+    let result = (|| -> io::Result<()> {
+        let describe = |out: &mut io::StdoutLock, index: usize, info: &GpuInfo| {
+            writeln!(
+                out,
+                "# gpu {index}: {} {} ({}, driver {})",
+                info.vendor, info.name, info.bus_id, info.driver_version
+            )
+        };
+        if options.format == OutputFormat::Text {
+            for (index, info) in infos.iter().enumerate() {
+                describe(&mut out, index, info)?;
+            }
+        }
+
+        let mut interval = options.interval();
+        let mut next = Instant::now();
+        let mut tick: u64 = 0;
+        loop {
+            if let Some(sampling) = alerts.reload(&mut config, options.alerts.as_deref()) {
+                let sampling = options.sampling_overrides.reloaded(&sampling);
+                interval = sampling.interval.unwrap_or(interval);
+            }
+            let known = devices.devices.len();
+            devices.reconnect();
+            for (index, (info, _)) in devices.devices.iter().enumerate().skip(known) {
+                if let Some(recorder) = &mut recorder {
+                    if let Err(e) = recorder.add_device(info) {
+                        log::warn!("recording: {e}");
+                    }
+                }
+                if options.format == OutputFormat::Text {
+                    describe(&mut out, index, info)?;
+                }
+            }
+            if options.format == OutputFormat::Text && tick.is_multiple_of(HEADER_EVERY) {
+                writeln!(out, "{HEADER}")?;
+            }
+            for (index, (info, link)) in devices.devices.iter_mut().enumerate() {
+                let was_offline = link.is_offline();
+                let (data, processes) = match link.sample() {
+                    // Waiting to be reconnected
+                    None => continue,
+                    Some(Ok(mut batch)) => {
+                        if was_offline {
+                            log::info!("gpu {index} is back online");
+                        }
+                        match batch.pop() {
+                            Some(sample) => sample,
+                            None => continue,
+                        }
+                    }
+                    Some(Err(e)) if link.is_offline() => {
+                        log::warn!("gpu {index} is offline: {e}");
+                        continue;
+                    }
+                    Some(Err(e)) => {
+                        log::warn!("gpu {index}: {e}");
+                        continue;
+                    }
+                };
+                if let Some(recorder) = &mut recorder {
+                    if let Err(e) = recorder.record(&data, &processes) {
+                        log::warn!("recording: {e}");
+                    }
+                }
+                if let Some(csv) = &mut csv {
+                    if let Err(e) = csv.write(info, &data) {
+                        log::warn!("csv: {e}");
+                    }
+                }
+                alerts.process(info, &data);
+                match options.format {
+                    OutputFormat::Text => writeln!(out, "{}", format_row(index, &data))?,
+                    OutputFormat::Ndjson => {
+                        let record = SampleRecord::new(info.clone(), data, processes);
+                        serde_json::to_writer(&mut out, &record)?;
+                        writeln!(out)?;
+                    }
+                }
+            }
+            out.flush()?;
+
+            tick += 1;
+            if options.count.is_some_and(|count| tick >= count) {
+                break;
+            }
+            // Sleep until the next tick rather than for a fixed interval, so
+            // sampling time does not accumulate as drift
+            next += interval;
+            std::thread::sleep(next.saturating_duration_since(Instant::now()));
+        }
+        Ok(())
+    })();
+
+    match result {
+        // Output piped into `head` and similar; not an error
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.map_err(|e| e.to_string()),
+    }
+}
@@ -0,0 +1,279 @@
+// Sample history of one device.
+//
+// Recent samples are kept as-is. Older data survives only as min/avg/max
+// rollups at 1 s, 1 min and 10 min resolution, so hours of history at a
+// 100 ms sampling interval stay small. `History::series` picks whichever
+// level shows the requested time span in a given number of points.
+use crate::data::{GpuData, Metric};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How long raw samples are kept, at most.
+pub const RAW_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+/// (resolution, retention) of every rollup tier, finest first.
+pub const TIERS: [(Duration, Duration); 3] = [
+    (Duration::from_secs(1), Duration::from_secs(3600)),
+    (Duration::from_secs(60), Duration::from_secs(24 * 3600)),
+    (Duration::from_secs(600), Duration::from_secs(24 * 3600)),
+];
+
+/// Every metric of a device over one time bucket, reduced three ways.
+#[derive(Clone, Debug)]
+pub struct Rollup {
+    /// Timestamp at which the bucket starts
+    pub start: f64,
+    /// Length of the bucket, in seconds
+    pub duration: f64,
+    pub min: GpuData,
+    pub avg: GpuData,
+    pub max: GpuData,
+}
+
+/// One point of a plotted series.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeriesPoint {
+    pub timestamp: f64,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+/// Which level of the history a series was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Raw,
+    /// Rollups of this many seconds
+    Rollup(u64),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Stats {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u32,
+}
+
+/// A rollup still receiving samples.
+#[derive(Clone, Debug)]
+struct Bucket {
+    start: f64,
+    device_id: String,
+    stats: [Option<Stats>; Metric::ALL.len()],
+}
+
+impl Bucket {
+    fn new(start: f64, device_id: &str) -> Self {
+        Self {
+            start,
+            device_id: device_id.to_string(),
+            stats: [None; Metric::ALL.len()],
+        }
+    }
+
+    fn add(&mut self, sample: &GpuData) {
+        for (stats, metric) in self.stats.iter_mut().zip(Metric::ALL) {
+            let Some(value) = sample.metric(metric) else {
+                continue;
+            };
+            let stats = stats.get_or_insert(Stats {
+                min: value,
+                max: value,
+                sum: 0.0,
+                count: 0,
+            });
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+            stats.sum += value;
+            stats.count += 1;
+        }
+    }
+
+    fn rollup(&self, duration: f64) -> Rollup {
+        let mut min = GpuData::empty(&self.device_id, self.start);
+        let mut avg = min.clone();
+        let mut max = min.clone();
+        for (stats, metric) in self.stats.iter().zip(Metric::ALL) {
+            min.set_metric(metric, stats.map(|s| s.min));
+            avg.set_metric(metric, stats.map(|s| s.sum / s.count as f64));
+            max.set_metric(metric, stats.map(|s| s.max));
+        }
+        Rollup {
+            start: self.start,
+            duration,
+            min,
+            avg,
+            max,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Tier {
+    resolution: f64,
+    retention: f64,
+    rollups: VecDeque<Rollup>,
+    current: Option<Bucket>,
+}
+
+impl Tier {
+    fn add(&mut self, sample: &GpuData) {
+        let start = (sample.timestamp / self.resolution).floor() * self.resolution;
+        if let Some(bucket) = self.current.take_if(|bucket| bucket.start != start) {
+            self.rollups.push_back(bucket.rollup(self.resolution));
+        }
+        self.current
+            .get_or_insert_with(|| Bucket::new(start, &sample.device_id))
+            .add(sample);
+    }
+
+    fn trim(&mut self, oldest: f64) {
+        while self
+            .rollups
+            .front()
+            .is_some_and(|r| r.start + r.duration < oldest)
+        {
+            self.rollups.pop_front();
+        }
+    }
+
+    /// Finished rollups followed by the one in progress.
+    fn rollups(&self) -> impl Iterator<Item = Rollup> + '_ {
+        self.rollups.iter().cloned().chain(
+            self.current
+                .as_ref()
+                .map(|bucket| bucket.rollup(self.resolution)),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct History {
+    /// Samples older than this many seconds are dropped from every level
+    window: f64,
+    raw: VecDeque<GpuData>,
+    tiers: Vec<Tier>,
+}
+
+impl History {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: window.as_secs_f64(),
+            raw: VecDeque::new(),
+            tiers: TIERS
+                .iter()
+                .map(|(resolution, retention)| Tier {
+                    resolution: resolution.as_secs_f64(),
+                    retention: retention.as_secs_f64(),
+                    rollups: VecDeque::new(),
+                    current: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Change how far back the history reaches. Shrinking it drops data on
+    /// the next `push`; growing it only keeps more from now on.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window.as_secs_f64();
+    }
+
+    /// Add a sample. Samples not newer than the latest one are ignored, as
+    /// a replay that has not advanced returns the same sample again.
+    pub fn push(&mut self, sample: GpuData) -> bool {
+        if self
+            .latest()
+            .is_some_and(|last| last.timestamp >= sample.timestamp)
+        {
+            return false;
+        }
+        let now = sample.timestamp;
+        for tier in &mut self.tiers {
+            tier.add(&sample);
+            tier.trim(now - tier.retention.min(self.window));
+        }
+        self.raw.push_back(sample);
+
+        let oldest_raw = now - RAW_RETENTION.as_secs_f64().min(self.window);
+        while self.raw.front().is_some_and(|d| d.timestamp < oldest_raw) {
+            self.raw.pop_front();
+        }
+        true
+    }
+
+    pub fn latest(&self) -> Option<&GpuData> {
+        self.raw.back()
+    }
+
+    /// Recent samples, oldest first.
+    pub fn raw(&self) -> &VecDeque<GpuData> {
+        &self.raw
+    }
+
+    /// The finest level that covers the last `span` seconds in at most
+    /// `max_points` points, or the coarsest level if none does.
+    pub fn resolution_for(&self, span: f64, max_points: usize) -> Resolution {
+        let max_points = max_points.max(1) as f64;
+        let raw_span = RAW_RETENTION.as_secs_f64().min(self.window);
+        let from = self.latest().map_or(0.0, |d| d.timestamp) - span;
+        let raw_points = self.raw.len() - self.raw.partition_point(|d| d.timestamp < from);
+        if span <= raw_span && raw_points as f64 <= max_points {
+            return Resolution::Raw;
+        }
+        let tier = self
+            .tiers
+            .iter()
+            .find(|t| span <= t.retention && span / t.resolution <= max_points)
+            .or(self.tiers.last())
+            .expect("at least one tier");
+        Resolution::Rollup(tier.resolution as u64)
+    }
+
+    /// `value` over the last `span` seconds, read from the level chosen by
+    /// `resolution_for`. Each rollup bucket is plotted at its midpoint.
+    pub fn series(
+        &self,
+        span: f64,
+        max_points: usize,
+        value: impl Fn(&GpuData) -> Option<f64>,
+    ) -> (Resolution, Vec<SeriesPoint>) {
+        let latest = self.latest().map_or(0.0, |d| d.timestamp);
+        let from = latest - span;
+        let resolution = self.resolution_for(span, max_points);
+        let points = match resolution {
+            Resolution::Raw => self
+                .raw
+                .iter()
+                .filter(|d| d.timestamp >= from)
+                .filter_map(|d| {
+                    let v = value(d)?;
+                    Some(SeriesPoint {
+                        timestamp: d.timestamp,
+                        min: v,
+                        avg: v,
+                        max: v,
+                    })
+                })
+                .collect(),
+            Resolution::Rollup(seconds) => self
+                .tiers
+                .iter()
+                .find(|t| t.resolution as u64 == seconds)
+                .into_iter()
+                .flat_map(Tier::rollups)
+                .filter(|r| r.start + r.duration >= from)
+                .filter_map(|r| {
+                    let avg = value(&r.avg)?;
+                    Some(SeriesPoint {
+                        timestamp: (r.start + r.duration / 2.0).min(latest),
+                        min: value(&r.min).unwrap_or(avg),
+                        avg,
+                        max: value(&r.max).unwrap_or(avg),
+                    })
+                })
+                .collect(),
+        };
+        (resolution, points)
+    }
+}
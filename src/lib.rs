@@ -1,3 +1,18 @@
+pub mod alerts;
 pub mod app;
+pub mod cli;
+pub mod config;
+pub mod csv_log;
 pub mod data;
+pub mod exporter;
+pub mod fdinfo;
+pub mod headless;
+pub mod history;
+pub mod logging;
 pub mod monitor;
+pub mod processes;
+pub mod recording;
+pub mod sampler;
+pub mod settings;
+pub mod signals;
+pub mod tui;
@@ -0,0 +1,52 @@
+// Log output on stderr through the `log` facade, at the verbosity set by
+// `--log-level` or `log.level` in the config file.
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Verbosity when none is configured.
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::Info;
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies such as wgpu are chatty; only their warnings and
+        // errors are of interest unless debugging
+        metadata.level() <= log::max_level()
+            && (metadata.target().starts_with("rgm")
+                || metadata.level() <= Level::Warn
+                || log::max_level() >= LevelFilter::Debug)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("rgm: {}: {}", level_label(record.level()), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn level_label(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warning",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// Send log records to stderr, at most as verbose as `level`. Only the first
+/// call installs the logger; later ones just change the level.
+pub fn init(level: LevelFilter) {
+    static LOGGER: StderrLogger = StderrLogger;
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
+
+/// Parse `off`, `error`, `warn`, `info`, `debug` or `trace`, ignoring case.
+pub fn parse_level(text: &str) -> Result<LevelFilter, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| "expected off, error, warn, info, debug or trace".to_string())
+}
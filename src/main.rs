@@ -1,17 +1,82 @@
 use eframe::egui::ViewportBuilder;
+use std::process::ExitCode;
 
+use rgm_ui::alerts::{configured_rules, AlertEngine};
 use rgm_ui::app::RgmApp;
+use rgm_ui::cli::{CliOptions, Mode, USAGE};
+use rgm_ui::config::ConfigWatcher;
+use rgm_ui::{exporter, headless, logging, signals, tui};
 
-fn main() {
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match CliOptions::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("rgm: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match options.mode {
+        Mode::Help => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Mode::Version => {
+            println!("rgm {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        _ => {}
+    }
+
+    // The config file provides defaults, which the command line overrides
+    let config = match ConfigWatcher::load(options.config.clone()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("rgm: {e}");
+            return ExitCode::from(2);
+        }
+    };
+    let options = match CliOptions::parse_onto(config.config().cli_defaults(), &args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("rgm: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    logging::init(options.log_level);
+
+    let result = match options.mode {
+        Mode::Headless => headless::run(&options, config),
+        Mode::Exporter => exporter::run(&options, config),
+        Mode::Tui => tui::run(&options, config),
+        Mode::Kill => signals::run(&options),
+        Mode::Gui => run_gui(options, config),
+        Mode::Help | Mode::Version => Ok(()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("rgm: {e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_gui(options: CliOptions, config: ConfigWatcher) -> Result<(), String> {
+    // Check the alert rules before opening a window
+    let alerts = AlertEngine::new(configured_rules(
+        config.config(),
+        options.alerts.as_deref(),
+    )?);
     let native_options = eframe::NativeOptions {
-        viewport: ViewportBuilder::default().with_inner_size([1000.0, 700.0]),
+        viewport: ViewportBuilder::default().with_inner_size(config.config().ui.window_size),
         ..Default::default()
     };
 
     eframe::run_native(
         "RGM",
         native_options,
-        Box::new(|cc| Ok(Box::new(RgmApp::new(cc)))),
+        Box::new(move |cc| Ok(Box::new(RgmApp::new(cc, &options, alerts, config)))),
     )
     .expect("Failed to start application");
+    Ok(())
 }
@@ -1,46 +1,307 @@
-use crate::data::{GpuData, GpuInfo, ProcessInfo};
+use crate::data::{GpuData, GpuInfo, GpuVendor, Metric, MetricError, ProcessInfo, ProcessType};
+use crate::fdinfo::{DrmClients, FdinfoScanner};
+use crate::processes::{merge_processes, HostProcesses};
 use nvml_wrapper::enum_wrappers::device::{Clock, PcieUtilCounter, TemperatureSensor};
-use nvml_wrapper::enums::device::UsedGpuMemory;
-use nvml_wrapper::Nvml;
+use nvml_wrapper::enums::device::{SampleValue, UsedGpuMemory};
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::struct_wrappers::device::ProcessUtilizationSample;
+use nvml_wrapper::structs::device::FieldId;
+use nvml_wrapper::sys_exports::field_id::{
+    NVML_FI_DEV_POWER_AVERAGE, NVML_FI_DEV_POWER_CURRENT_LIMIT,
+};
+use nvml_wrapper::{Device, Nvml};
 use thiserror::Error;
 
 use amdgpu_sysfs::gpu_handle::GpuHandle;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 #[derive(Error, Debug)]
 pub enum MonitorError {
-    #[error("NVML initialization failed: {0}")]
-    NvmlInit(#[from] nvml_wrapper::error::NvmlError),
+    #[error("{library} could not be loaded: {reason}")]
+    LibraryNotFound {
+        library: &'static str,
+        reason: String,
+    },
+    #[error("the {0} kernel driver is not loaded")]
+    DriverNotLoaded(&'static str),
+    #[error("permission denied on {0}")]
+    PermissionDenied(String),
+    #[error("the GPU is lost: it fell off the bus or was unplugged")]
+    DeviceLost,
     #[error("Device not found at index {0}")]
     DeviceNotFound(u32),
+    #[error("no {0} device found")]
+    NoDevice(&'static str),
+    #[error("reading {metric} failed: {message}")]
+    MetricRead { metric: Metric, message: String },
+    #[error("NVML call failed: {0}")]
+    Nvml(NvmlError),
     #[error("Failed to get data: {0}")]
     SamplingFailed(String),
+    #[error("no device matches {0}")]
+    NoMatchingDevice(String),
+    #[error("recording: {0}")]
+    Recording(String),
 }
 
+impl MonitorError {
+    /// Short category, for counting errors in the status bars.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MonitorError::LibraryNotFound { .. } => "library not found",
+            MonitorError::DriverNotLoaded(_) => "driver not loaded",
+            MonitorError::PermissionDenied(_) => "permission denied",
+            MonitorError::DeviceLost => "device lost",
+            MonitorError::DeviceNotFound(_)
+            | MonitorError::NoDevice(_)
+            | MonitorError::NoMatchingDevice(_) => "no device",
+            MonitorError::MetricRead { .. } => "metric read",
+            MonitorError::Nvml(_) => "NVML",
+            MonitorError::SamplingFailed(_) => "sampling",
+            MonitorError::Recording(_) => "recording",
+        }
+    }
+}
+
+impl From<NvmlError> for MonitorError {
+    fn from(error: NvmlError) -> Self {
+        match error {
+            NvmlError::LibloadingError(e) => MonitorError::LibraryNotFound {
+                library: "libnvidia-ml.so",
+                reason: e.to_string(),
+            },
+            NvmlError::DriverNotLoaded => MonitorError::DriverNotLoaded("nvidia"),
+            NvmlError::NoPermission => MonitorError::PermissionDenied("/dev/nvidia*".into()),
+            NvmlError::GpuLost => MonitorError::DeviceLost,
+            error => MonitorError::Nvml(error),
+        }
+    }
+}
+
+impl From<MetricError> for MonitorError {
+    fn from(error: MetricError) -> Self {
+        MonitorError::MetricRead {
+            metric: error.metric,
+            message: error.message,
+        }
+    }
+}
+
+/// Default mount point of sysfs; backends accept an alternate root for testing.
+pub const SYSFS_ROOT: &str = "/sys";
+/// Default mount point of procfs.
+pub const PROCFS_ROOT: &str = "/proc";
+
 pub trait GpuMonitor: Send + Sync {
     fn get_static_info(&self) -> GpuInfo;
     fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError>;
+
+    /// Every sample taken since the previous call, oldest first. A live
+    /// device only has the current one; a replay running faster than the
+    /// sampling interval has several.
+    fn sample_batch(&self) -> Result<Vec<(GpuData, Vec<ProcessInfo>)>, MonitorError> {
+        Ok(vec![self.sample()?])
+    }
+}
+
+/// Outcome of reading one metric: `Ok(None)` if the device does not provide
+/// it, `Err` if it does but this read failed.
+type Reading<T> = Result<Option<T>, String>;
+
+/// Unwrap a reading into a `GpuData` field, recording a read error.
+fn take<T>(errors: &mut Vec<MetricError>, metric: Metric, reading: Reading<T>) -> Option<T> {
+    reading.unwrap_or_else(|message| {
+        errors.push(MetricError { metric, message });
+        None
+    })
+}
+
+/// Milliseconds since `started`, for `GpuData::collection_time`.
+fn elapsed_ms(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+/// NVML reports metrics a device lacks as `NotSupported`. Errors that
+/// concern the whole device, like a lost GPU, fail the sample instead of
+/// one metric.
+fn nvml_reading<T>(result: Result<T, NvmlError>) -> Result<Reading<T>, MonitorError> {
+    match result {
+        Ok(value) => Ok(Ok(Some(value))),
+        Err(NvmlError::NotSupported) => Ok(Ok(None)),
+        Err(e @ (NvmlError::GpuLost | NvmlError::ResetRequired | NvmlError::DriverNotLoaded)) => {
+            Err(e.into())
+        }
+        Err(e) => Ok(Err(e.to_string())),
+    }
 }
 
 // ── NVIDIA Backend ──────────────────────────────────────────────────────────
 
+/// Field values fetched in one `nvmlDeviceGetFieldValues` call per sample.
+/// NVML has no field ids for the GPU temperature, clocks or fan speed, so
+/// those stay separate calls.
+const BATCHED_FIELDS: [u32; 2] = [NVML_FI_DEV_POWER_AVERAGE, NVML_FI_DEV_POWER_CURRENT_LIMIT];
+
+// The NVML session new monitors are created from. Every probe starts a new
+// one, so a rescan after a driver reload or GPU reset sees the devices as
+// they are now; each monitor keeps the session it came from alive.
+static SESSION: Mutex<Option<Arc<Nvml>>> = Mutex::new(None);
+
+/// The current NVML session, or a new one if `renew` is set or there is
+/// none yet.
+fn nvml_session(renew: bool) -> Result<Arc<Nvml>, MonitorError> {
+    let mut session = SESSION.lock().unwrap();
+    if renew {
+        *session = None;
+    }
+    let nvml = match session.as_ref() {
+        Some(nvml) => Arc::clone(nvml),
+        None => Arc::new(Nvml::init()?),
+    };
+    *session = Some(Arc::clone(&nvml));
+    Ok(nvml)
+}
+
+/// Errors after which a session is of no further use and must be renewed.
+fn session_lost(error: &NvmlError) -> bool {
+    matches!(
+        error,
+        NvmlError::GpuLost | NvmlError::DriverNotLoaded | NvmlError::Uninitialized
+    )
+}
+
+// NVML device handle, looked up once instead of on every sample. Handles
+// are opaque pointers into the NVML session, valid from any thread until
+// that session is shut down.
+struct DeviceHandle(*mut std::ffi::c_void);
+
+// SAFETY: NVML is thread safe and the handle is never dereferenced on the
+// Rust side; nvml-wrapper's `Device` is Send and Sync for the same reason
+unsafe impl Send for DeviceHandle {}
+unsafe impl Sync for DeviceHandle {}
+
 pub struct NvmlMonitor {
-    nvml: Nvml,
-    device_index: u32,
+    /// The session `handle` belongs to, kept alive by the monitor
+    nvml: Arc<Nvml>,
+    handle: DeviceHandle,
+    bus_id: String,
+    procfs_root: PathBuf,
+    host: HostProcesses,
+    /// Timestamp of the newest process utilization sample seen, in µs, so
+    /// each sample only asks NVML for newer ones
+    last_process_sample: AtomicU64,
     start_time: std::time::Instant,
 }
 
 impl NvmlMonitor {
     pub fn new(device_index: u32) -> Result<Self, MonitorError> {
-        let nvml = Nvml::init()?;
-        // Check if the device exists
-        nvml.device_by_index(device_index)?;
+        let result = match Self::with_nvml(nvml_session(false)?, device_index) {
+            Err(e) if session_lost(&e) => Self::with_nvml(nvml_session(true)?, device_index),
+            result => result,
+        };
+        result.map_err(|e| device_error(device_index, e))
+    }
+
+    /// Create one monitor per NVIDIA device, sharing a new NVML session.
+    /// Devices that fail to initialise are skipped; an error is returned
+    /// only if none succeed.
+    pub fn enumerate() -> Result<Vec<Self>, MonitorError> {
+        let nvml = nvml_session(true)?;
+        let count = nvml.device_count()?;
+        let mut monitors = Vec::new();
+        let mut last_error = None;
+        for index in 0..count {
+            match Self::with_nvml(Arc::clone(&nvml), index) {
+                Ok(monitor) => monitors.push(monitor),
+                Err(e) => {
+                    let e = device_error(index, e);
+                    log::warn!("skipping NVIDIA device {index}: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match (monitors.is_empty(), last_error) {
+            (true, Some(e)) => Err(e),
+            (true, None) => Err(MonitorError::NoDevice("NVIDIA")),
+            (false, _) => Ok(monitors),
+        }
+    }
+
+    fn with_nvml(nvml: Arc<Nvml>, device_index: u32) -> Result<Self, NvmlError> {
+        let (handle, bus_id) = {
+            let device = nvml.device_by_index(device_index)?;
+            let bus_id = device
+                .pci_info()
+                .map(|pci| pci.bus_id)
+                .unwrap_or_else(|_| format!("nvml:{device_index}"));
+            // SAFETY: the handle is only turned back into a `Device` together
+            // with the session it came from, see `device()`
+            (DeviceHandle(unsafe { device.handle() }.cast()), bus_id)
+        };
         Ok(Self {
             nvml,
-            device_index,
+            handle,
+            bus_id,
+            procfs_root: PathBuf::from(PROCFS_ROOT),
+            host: HostProcesses::default(),
+            last_process_sample: AtomicU64::new(0),
             start_time: std::time::Instant::now(),
         })
     }
+
+    /// The device this monitor samples, from the cached handle.
+    fn device(&self) -> Device<'_> {
+        // SAFETY: the handle came from `self.nvml`, which the monitor keeps
+        // alive, so its session has not been shut down
+        unsafe { Device::new(self.handle.0.cast(), &self.nvml) }
+    }
+
+    /// Resolve process details against `procfs_root` instead of `/proc`.
+    pub fn with_procfs_root(mut self, procfs_root: impl Into<PathBuf>) -> Self {
+        self.procfs_root = procfs_root.into();
+        self.host = HostProcesses::new(&self.procfs_root);
+        self
+    }
+
+    /// Set SM, encoder and decoder utilization from the samples NVML took
+    /// since the previous call. Left unset on GPUs that do not support it.
+    fn apply_process_utilization(&self, device: &Device, processes: &mut [ProcessInfo]) {
+        let since = self.last_process_sample.load(Ordering::Relaxed);
+        let samples = match device.process_utilization_stats(since) {
+            Ok(samples) => samples,
+            // No process was active since the last call
+            Err(NvmlError::NotFound) => Vec::new(),
+            Err(_) => return,
+        };
+
+        // NVML keeps a sample per process per period; use the newest
+        let mut latest: HashMap<u32, &ProcessUtilizationSample> = HashMap::new();
+        for sample in &samples {
+            let entry = latest.entry(sample.pid).or_insert(sample);
+            if sample.timestamp > entry.timestamp {
+                *entry = sample;
+            }
+        }
+        if let Some(newest) = samples.iter().map(|s| s.timestamp).max() {
+            self.last_process_sample.store(newest, Ordering::Relaxed);
+        }
+
+        for process in processes {
+            // Processes without a sample were idle
+            let (sm, enc, dec) = latest
+                .get(&process.pid)
+                .map_or((0, 0, 0), |s| (s.sm_util, s.enc_util, s.dec_util));
+            process.gpu_utilization = sm as f32;
+            process.sm_utilization = Some(sm as f32);
+            process.encoder_utilization = Some(enc as f32);
+            process.decoder_utilization = Some(dec as f32);
+        }
+    }
 }
 
 impl GpuMonitor for NvmlMonitor {
@@ -50,188 +311,284 @@ impl GpuMonitor for NvmlMonitor {
             .sys_driver_version()
             .unwrap_or_else(|_| "N/A".to_string());
 
-        let Ok(device) = self.nvml.device_by_index(self.device_index) else {
-            return GpuInfo {
-                name: "N/A".to_string(),
-                uuid: "N/A".to_string(),
-                driver_version,
-                vbios_version: "N/A".to_string(),
-                pcie_gen: 0,
-                pcie_width: 0,
-            };
-        };
-
+        let device = self.device();
         GpuInfo {
+            vendor: GpuVendor::Nvidia,
             name: device.name().unwrap_or_else(|_| "N/A".to_string()),
             uuid: device.uuid().unwrap_or_else(|_| "N/A".to_string()),
+            bus_id: self.bus_id.clone(),
             driver_version,
             vbios_version: device.vbios_version().unwrap_or_else(|_| "N/A".to_string()),
             pcie_gen: device.current_pcie_link_gen().unwrap_or(0),
             pcie_width: device.current_pcie_link_width().unwrap_or(0),
+            max_gpu_clock: device.max_clock_info(Clock::Graphics).ok(),
         }
     }
 
     fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError> {
-        // Temporarily get the device object when needed
-        let device = self.nvml.device_by_index(self.device_index)?;
+        let started = Instant::now();
+        let device = self.device();
+        let mut fields = field_values(&device)?;
 
-        let (util, mem, temp) = (
-            device.utilization_rates()?,
-            device.memory_info()?,
-            device.temperature(TemperatureSensor::Gpu)?,
+        // Each metric is read on its own, so one that fails (as some do on
+        // vGPU and MIG setups) leaves the others intact
+        let mut errors = Vec::new();
+        let utilization = take(
+            &mut errors,
+            Metric::Utilization,
+            nvml_reading(device.utilization_rates())?,
+        )
+        .map(|util| util.gpu as f32);
+        let memory = match nvml_reading(device.memory_info())? {
+            Ok(memory) => memory,
+            Err(message) => {
+                errors.push(MetricError {
+                    metric: Metric::MemoryUsed,
+                    message: message.clone(),
+                });
+                errors.push(MetricError {
+                    metric: Metric::MemoryTotal,
+                    message,
+                });
+                None
+            }
+        };
+        let temperature = take(
+            &mut errors,
+            Metric::Temperature,
+            nvml_reading(device.temperature(TemperatureSensor::Gpu))?,
         );
 
-        let gpu_clock = device.clock_info(Clock::Graphics).unwrap_or(0);
-        let mem_clock = device.clock_info(Clock::Memory).unwrap_or(0);
+        let gpu_clock = take(
+            &mut errors,
+            Metric::GpuClock,
+            nvml_reading(device.clock_info(Clock::Graphics))?,
+        );
+        let memory_clock = take(
+            &mut errors,
+            Metric::MemoryClock,
+            nvml_reading(device.clock_info(Clock::Memory))?,
+        );
 
-        let (power_usage, power_limit) =
-            match (device.power_usage(), device.power_management_limit()) {
-                (Ok(usage), Ok(limit)) => (usage as f64 / 1000.0, limit as f64 / 1000.0),
-                _ => (0.0, 0.0),
-            };
+        // NVML reports power in mW, averaged over the last second like
+        // `nvmlDeviceGetPowerUsage`. Drivers without the batched fields fall
+        // back to the individual calls.
+        let power_usage = match fields.remove(&NVML_FI_DEV_POWER_AVERAGE) {
+            Some(reading) => take(&mut errors, Metric::PowerUsage, reading),
+            None => take(
+                &mut errors,
+                Metric::PowerUsage,
+                nvml_reading(device.power_usage())?,
+            )
+            .map(f64::from),
+        }
+        .map(|mw| mw / 1000.0);
+        let power_limit = match fields.remove(&NVML_FI_DEV_POWER_CURRENT_LIMIT) {
+            Some(reading) => take(&mut errors, Metric::PowerLimit, reading),
+            None => take(
+                &mut errors,
+                Metric::PowerLimit,
+                nvml_reading(device.power_management_limit())?,
+            )
+            .map(f64::from),
+        }
+        .map(|mw| mw / 1000.0);
 
-        let fan_speed = device.fan_speed(0).unwrap_or(0);
+        let fan_speed = take(
+            &mut errors,
+            Metric::FanSpeed,
+            nvml_reading(device.fan_speed(0))?,
+        );
 
-        let (pcie_tx, pcie_rx) = match (
-            device.pcie_throughput(PcieUtilCounter::Send),
-            device.pcie_throughput(PcieUtilCounter::Receive),
-        ) {
-            (Ok(tx), Ok(rx)) => (tx as f64 / 1024.0, rx as f64 / 1024.0),
-            _ => (0.0, 0.0),
-        };
+        // NVML reports PCIe throughput in KB/s
+        let pcie_throughput_tx = take(
+            &mut errors,
+            Metric::PcieThroughputTx,
+            nvml_reading(device.pcie_throughput(PcieUtilCounter::Send))?,
+        )
+        .map(|kb| kb as f64 / 1024.0);
+        let pcie_throughput_rx = take(
+            &mut errors,
+            Metric::PcieThroughputRx,
+            nvml_reading(device.pcie_throughput(PcieUtilCounter::Receive))?,
+        )
+        .map(|kb| kb as f64 / 1024.0);
 
-        let gpu_data = GpuData {
+        let mut gpu_data = GpuData {
+            device_id: self.bus_id.clone(),
             timestamp: self.start_time.elapsed().as_secs_f64(),
-            utilization: util.gpu as f32,
-            memory_used: mem.used as f64 / 1024.0 / 1024.0 / 1024.0,
-            memory_total: mem.total as f64 / 1024.0 / 1024.0 / 1024.0,
-            temperature: temp,
+            utilization,
+            memory_used: memory
+                .as_ref()
+                .map(|mem| mem.used as f64 / 1024.0 / 1024.0 / 1024.0),
+            memory_total: memory
+                .as_ref()
+                .map(|mem| mem.total as f64 / 1024.0 / 1024.0 / 1024.0),
+            temperature,
             gpu_clock,
-            memory_clock: mem_clock,
+            memory_clock,
             power_usage,
             power_limit,
             fan_speed,
-            pcie_throughput_tx: pcie_tx,
-            pcie_throughput_rx: pcie_rx,
+            pcie_throughput_tx,
+            pcie_throughput_rx,
+            collection_time: None,
+            errors,
         };
 
+        // CUDA jobs hold compute contexts, desktop clients graphics ones;
+        // a process with both is listed once
         let mut process_infos = Vec::new();
-        if let Ok(procs) = device.running_graphics_processes() {
-            for proc in procs {
-                let proc_name = std::fs::read_to_string(format!("/proc/{}/comm", proc.pid))
-                    .map(|s| s.trim().to_string())
-                    .unwrap_or_else(|_| "unknown".to_string());
+        for (procs, process_type) in [
+            (device.running_compute_processes(), ProcessType::Compute),
+            (device.running_graphics_processes(), ProcessType::Graphics),
+        ] {
+            for proc in procs.unwrap_or_default() {
                 let memory_usage = match proc.used_gpu_memory {
                     UsedGpuMemory::Used(v) => v,
                     _ => 0,
                 };
                 process_infos.push(ProcessInfo {
                     pid: proc.pid,
-                    name: proc_name,
                     memory_usage,
-                    cpu_percent: 0.0,
+                    process_type: Some(process_type),
+                    ..Default::default()
                 });
             }
         }
+        let mut process_infos = merge_processes(process_infos);
+        for process in &mut process_infos {
+            process.name = read_process_name(&self.procfs_root, process.pid);
+        }
+        self.apply_process_utilization(&device, &mut process_infos);
+        self.host.fill(&mut process_infos);
 
+        gpu_data.collection_time = Some(elapsed_ms(started));
         Ok((gpu_data, process_infos))
     }
 }
 
+/// `BATCHED_FIELDS` in a single NVML call, as readings by field id. Fields
+/// the driver does not know are left out, to be read on their own; errors
+/// concerning the whole device fail the sample, as in `nvml_reading`.
+fn field_values(device: &Device) -> Result<HashMap<u32, Reading<f64>>, MonitorError> {
+    let ids = BATCHED_FIELDS.map(FieldId);
+    let samples = match nvml_reading(device.field_values_for(&ids))? {
+        Ok(Some(samples)) => samples,
+        _ => return Ok(HashMap::new()),
+    };
+
+    let mut readings = HashMap::new();
+    for (id, sample) in BATCHED_FIELDS.into_iter().zip(samples) {
+        let value = sample.and_then(|sample| sample.value);
+        // Both power fields are unsigned ints, in mW
+        let reading = match nvml_reading(value)? {
+            Ok(Some(SampleValue::U32(v))) => Ok(Some(v.into())),
+            Ok(Some(other)) => Err(format!("unexpected field value {other:?}")),
+            Ok(None) => continue,
+            Err(message) => Err(message),
+        };
+        readings.insert(id, reading);
+    }
+    Ok(readings)
+}
+
+/// An error looking up NVIDIA device `index`.
+fn device_error(index: u32, error: NvmlError) -> MonitorError {
+    match error {
+        NvmlError::InvalidArg | NvmlError::NotFound => MonitorError::DeviceNotFound(index),
+        error => error.into(),
+    }
+}
+
 // ── AMD Backend ─────────────────────────────────────────────────────────────
 
 pub struct AmdgpuMonitor {
     gpu_handle: GpuHandle,
+    /// `/sys/class/drm/cardN/device`
+    device_path: PathBuf,
+    bus_id: String,
+    hwmon: Hwmon,
+    fdinfo: FdinfoScanner,
     start_time: std::time::Instant,
 }
 
 impl AmdgpuMonitor {
     /// Try to find and initialise the first AMD GPU driven by `amdgpu`.
     pub fn new() -> Result<Self, MonitorError> {
-        let sysfs_path = Self::find_amdgpu_device()
-            .ok_or_else(|| MonitorError::SamplingFailed("No amdgpu device found".into()))?;
+        let sysfs_path = find_drm_devices(Path::new(SYSFS_ROOT), &["amdgpu"])
+            .into_iter()
+            .next()
+            .ok_or(MonitorError::NoDevice("amdgpu"))?;
 
-        let gpu_handle = GpuHandle::new_from_path(sysfs_path)
-            .map_err(|e| MonitorError::SamplingFailed(format!("amdgpu_sysfs init: {e}")))?;
+        Self::from_path(sysfs_path, &DrmClients::host())
+    }
 
-        Ok(Self {
-            gpu_handle,
-            start_time: std::time::Instant::now(),
-        })
+    /// Initialise every AMD GPU driven by `amdgpu`. Cards that fail to
+    /// initialise are skipped; an error is returned only if none succeed.
+    pub fn enumerate() -> Result<Vec<Self>, MonitorError> {
+        Self::enumerate_in(Path::new(SYSFS_ROOT), &DrmClients::host())
     }
 
-    /// Scan `/sys/class/drm/card*/device/` for the first device using the
-    /// `amdgpu` kernel driver.
-    fn find_amdgpu_device() -> Option<PathBuf> {
-        let drm_dir = std::fs::read_dir("/sys/class/drm").ok()?;
-        let mut cards: Vec<_> = drm_dir
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                let name = e.file_name();
-                let name = name.to_string_lossy();
-                // Match "card0", "card1", ... but not "card0-DP-1" etc.
-                name.starts_with("card") && name[4..].chars().all(|c| c.is_ascii_digit())
-            })
-            .collect();
-        cards.sort_by_key(|e| e.file_name());
-
-        for entry in cards {
-            let device_path = entry.path().join("device");
-            let uevent_path = device_path.join("uevent");
-            if let Ok(uevent) = std::fs::read_to_string(&uevent_path) {
-                if uevent.lines().any(|l| l == "DRIVER=amdgpu") {
-                    return Some(device_path);
-                }
+    /// Like [`AmdgpuMonitor::enumerate`], but scans `sysfs_root/class/drm`
+    /// instead of `/sys/class/drm` and takes processes from `drm_clients`,
+    /// so fake sysfs and procfs trees can drive the backend.
+    pub fn enumerate_in(
+        sysfs_root: &Path,
+        drm_clients: &Arc<DrmClients>,
+    ) -> Result<Vec<Self>, MonitorError> {
+        let mut monitors = Vec::new();
+        let mut last_error = None;
+        for path in find_drm_devices(sysfs_root, &["amdgpu"]) {
+            match Self::from_path(path, drm_clients) {
+                Ok(monitor) => monitors.push(monitor),
+                Err(e) => last_error = Some(e),
             }
         }
-        None
-    }
 
-    /// Read the "edge" (or first available) temperature in °C from hwmon.
-    fn read_temperature(&self) -> u32 {
-        if let Some(hw_mon) = self.gpu_handle.hw_monitors.first() {
-            let temps = hw_mon.get_temps();
-            // Prefer "edge", fall back to any available sensor
-            if let Some(t) = temps.get("edge") {
-                return t.current.unwrap_or(0.0) as u32;
-            }
-            if let Some(t) = temps.values().next() {
-                return t.current.unwrap_or(0.0) as u32;
-            }
+        match (monitors.is_empty(), last_error) {
+            (true, Some(e)) => Err(e),
+            (true, None) => Err(MonitorError::NoDevice("amdgpu")),
+            (false, _) => Ok(monitors),
         }
-        0
     }
 
-    /// Fan speed as a percentage (0-100). Returns 0 for fanless iGPUs.
-    fn read_fan_speed(&self) -> u32 {
-        if let Some(hw_mon) = self.gpu_handle.hw_monitors.first() {
-            // PWM value is 0-255, convert to percentage
-            if let Ok(pwm) = hw_mon.get_fan_pwm() {
-                return (pwm as u32 * 100) / 255;
-            }
-        }
-        0
+    /// Initialise the AMD GPU whose sysfs device directory is `sysfs_path`.
+    pub fn from_path(
+        sysfs_path: PathBuf,
+        drm_clients: &Arc<DrmClients>,
+    ) -> Result<Self, MonitorError> {
+        let bus_id = read_uevent_value(&sysfs_path, "PCI_SLOT_NAME")
+            .unwrap_or_else(|| sysfs_path.display().to_string());
+
+        let gpu_handle = GpuHandle::new_from_path(sysfs_path.clone())
+            .map_err(|e| amdgpu_init_error(&sysfs_path, e))?;
+
+        Ok(Self {
+            gpu_handle,
+            hwmon: Hwmon::discover(&sysfs_path),
+            device_path: sysfs_path,
+            fdinfo: FdinfoScanner::shared(drm_clients, bus_id.clone()),
+            bus_id,
+            start_time: std::time::Instant::now(),
+        })
     }
 
-    /// Parse strings like "8.0 GT/s PCIe" to PCIe generation.
-    fn parse_pcie_gen(speed: &str) -> Option<u32> {
-        let rate = speed
-            .split_whitespace()
-            .find_map(|part| part.parse::<f32>().ok())?;
+    /// Scan `procfs_root` instead of `/proc` for per-process usage.
+    pub fn with_procfs_root(mut self, procfs_root: impl Into<PathBuf>) -> Self {
+        self.fdinfo = FdinfoScanner::new(procfs_root, self.bus_id.clone());
+        self
+    }
 
-        if rate >= 31.5 {
-            Some(5)
-        } else if rate >= 15.5 {
-            Some(4)
-        } else if rate >= 7.5 {
-            Some(3)
-        } else if rate >= 4.5 {
-            Some(2)
-        } else if rate >= 2.4 {
-            Some(1)
-        } else {
-            None
-        }
+    /// Average power in W, falling back to the instantaneous reading on
+    /// cards without `power1_average`.
+    fn read_power(&self) -> Reading<f64> {
+        let average = self.hwmon.read::<u64>("power1_average")?;
+        let microwatts = match average {
+            Some(power) => Some(power),
+            None => self.hwmon.read::<u64>("power1_input")?,
+        };
+        Ok(microwatts.map(|p| p as f64 / 1_000_000.0))
     }
 }
 
@@ -263,90 +620,639 @@ impl GpuMonitor for AmdgpuMonitor {
             .gpu_handle
             .get_current_link_speed()
             .ok()
-            .and_then(|s| Self::parse_pcie_gen(&s))
+            .and_then(|s| parse_pcie_gen(&s))
             .unwrap_or(0);
 
         GpuInfo {
+            vendor: GpuVendor::Amd,
             name,
             uuid: "N/A".to_string(),
+            bus_id: self.bus_id.clone(),
             driver_version,
             vbios_version,
             pcie_gen,
             pcie_width,
+            max_gpu_clock: None,
         }
     }
 
     fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError> {
-        let utilization = self.gpu_handle.get_busy_percent().unwrap_or(0) as f32;
+        let started = Instant::now();
+        check_present(&self.device_path)?;
+        let mut errors = Vec::new();
+        let mut data = GpuData::empty(&self.bus_id, self.start_time.elapsed().as_secs_f64());
 
-        // VRAM – may be unavailable on iGPUs
-        let memory_used =
-            self.gpu_handle.get_used_vram().unwrap_or(0) as f64 / 1024.0 / 1024.0 / 1024.0;
-        let memory_total =
-            self.gpu_handle.get_total_vram().unwrap_or(0) as f64 / 1024.0 / 1024.0 / 1024.0;
+        data.utilization = take(
+            &mut errors,
+            Metric::Utilization,
+            read_sysfs_metric::<u32>(&self.device_path.join("gpu_busy_percent")),
+        )
+        .map(|percent| percent as f32);
 
-        let temperature = self.read_temperature();
+        // VRAM – not exposed on most iGPUs
+        data.memory_used = take(
+            &mut errors,
+            Metric::MemoryUsed,
+            read_sysfs_metric::<u64>(&self.device_path.join("mem_info_vram_used")),
+        )
+        .map(|bytes| bytes as f64 / 1024.0 / 1024.0 / 1024.0);
+        data.memory_total = take(
+            &mut errors,
+            Metric::MemoryTotal,
+            read_sysfs_metric::<u64>(&self.device_path.join("mem_info_vram_total")),
+        )
+        .map(|bytes| bytes as f64 / 1024.0 / 1024.0 / 1024.0);
 
-        // Clocks from hwmon
-        let (gpu_clock, memory_clock) = if let Some(hw_mon) = self.gpu_handle.hw_monitors.first() {
-            (
-                hw_mon.get_gpu_clockspeed().unwrap_or(0) as u32,
-                hw_mon.get_vram_clockspeed().unwrap_or(0) as u32,
-            )
-        } else {
-            (0, 0)
-        };
+        data.temperature = take(
+            &mut errors,
+            Metric::Temperature,
+            self.hwmon.temperature(Some("edge")),
+        )
+        .map(|millidegrees| (millidegrees / 1000) as u32);
+
+        // hwmon clocks are in Hz
+        data.gpu_clock = take(
+            &mut errors,
+            Metric::GpuClock,
+            self.hwmon.read::<u64>("freq1_input"),
+        )
+        .map(|hz| (hz / 1_000_000) as u32);
+        data.memory_clock = take(
+            &mut errors,
+            Metric::MemoryClock,
+            self.hwmon.read::<u64>("freq2_input"),
+        )
+        .map(|hz| (hz / 1_000_000) as u32);
 
-        // Power from hwmon
-        let (power_usage, power_limit) = if let Some(hw_mon) = self.gpu_handle.hw_monitors.first() {
-            let usage = hw_mon
-                .get_power_average()
-                .or_else(|_| hw_mon.get_power_input())
-                .unwrap_or(0.0);
-            let cap = hw_mon.get_power_cap().unwrap_or(0.0);
-            (usage, cap)
-        } else {
-            (0.0, 0.0)
+        data.power_usage = take(&mut errors, Metric::PowerUsage, self.read_power());
+        data.power_limit = take(
+            &mut errors,
+            Metric::PowerLimit,
+            self.hwmon.read::<u64>("power1_cap"),
+        )
+        .map(|uw| uw as f64 / 1_000_000.0);
+
+        // PWM value is 0-255, convert to percentage; absent on fanless iGPUs
+        data.fan_speed = take(
+            &mut errors,
+            Metric::FanSpeed,
+            self.hwmon.read::<u32>("pwm1"),
+        )
+        .map(|pwm| pwm * 100 / 255);
+
+        // amdgpu sysfs does not expose PCIe throughput counters
+        data.errors = errors;
+
+        let processes = self.fdinfo.scan();
+        data.collection_time = Some(elapsed_ms(started));
+        Ok((data, processes))
+    }
+}
+
+// ── Intel Backend ───────────────────────────────────────────────────────────
+
+pub struct IntelMonitor {
+    /// `/sys/class/drm/cardN/device`
+    device_path: PathBuf,
+    /// `/sys/class/drm/cardN`, where i915 exposes its GT frequency files
+    card_path: PathBuf,
+    driver: String,
+    bus_id: String,
+    hwmon: Hwmon,
+    fdinfo: FdinfoScanner,
+    /// Last `energy1_input` reading (µJ), used to derive power draw
+    last_energy: Mutex<Option<(std::time::Instant, u64)>>,
+    start_time: std::time::Instant,
+}
+
+impl IntelMonitor {
+    /// Initialise every Intel GPU driven by `i915` or `xe`.
+    pub fn enumerate() -> Result<Vec<Self>, MonitorError> {
+        Self::enumerate_in(Path::new(SYSFS_ROOT), &DrmClients::host())
+    }
+
+    /// Like [`IntelMonitor::enumerate`], but scans `sysfs_root/class/drm`
+    /// and takes processes from `drm_clients`.
+    pub fn enumerate_in(
+        sysfs_root: &Path,
+        drm_clients: &Arc<DrmClients>,
+    ) -> Result<Vec<Self>, MonitorError> {
+        let monitors: Vec<Self> = find_drm_devices(sysfs_root, &["i915", "xe"])
+            .into_iter()
+            .map(|path| Self::from_path(path, drm_clients))
+            .collect();
+        if monitors.is_empty() {
+            return Err(MonitorError::NoDevice("i915 or xe"));
+        }
+        Ok(monitors)
+    }
+
+    /// Initialise the Intel GPU whose sysfs device directory is `device_path`.
+    pub fn from_path(device_path: PathBuf, drm_clients: &Arc<DrmClients>) -> Self {
+        let card_path = device_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| device_path.clone());
+        let driver = read_uevent_value(&device_path, "DRIVER").unwrap_or_default();
+        let bus_id = read_uevent_value(&device_path, "PCI_SLOT_NAME")
+            .unwrap_or_else(|| device_path.display().to_string());
+
+        Self {
+            hwmon: Hwmon::discover(&device_path),
+            device_path,
+            card_path,
+            driver,
+            fdinfo: FdinfoScanner::shared(drm_clients, bus_id.clone()),
+            bus_id,
+            last_energy: Mutex::new(None),
+            start_time: std::time::Instant::now(),
+        }
+    }
+
+    /// Scan `procfs_root` instead of `/proc` for per-process usage.
+    pub fn with_procfs_root(mut self, procfs_root: impl Into<PathBuf>) -> Self {
+        self.fdinfo = FdinfoScanner::new(procfs_root, self.bus_id.clone());
+        self
+    }
+
+    /// Read a GT frequency in MHz. i915 exposes `gt_<kind>_freq_mhz` on the
+    /// card; xe exposes `tile0/gt0/freq0/<kind>_freq` on the device.
+    fn read_gt_freq(&self, kind: &str) -> Reading<u32> {
+        match read_sysfs_metric(&self.card_path.join(format!("gt_{kind}_freq_mhz")))? {
+            Some(freq) => Ok(Some(freq)),
+            None => read_sysfs_metric(
+                &self
+                    .device_path
+                    .join(format!("tile0/gt0/freq0/{kind}_freq")),
+            ),
+        }
+    }
+
+    /// Average power in W since the previous sample, derived from the
+    /// cumulative energy counter. Unavailable on the first sample, which
+    /// has no baseline.
+    fn read_power(&self) -> Reading<f64> {
+        let Some(energy) = self.hwmon.read::<u64>("energy1_input")? else {
+            // Some platforms expose an instantaneous reading instead
+            return Ok(self
+                .hwmon
+                .read::<u64>("power1_input")?
+                .map(|p| p as f64 / 1_000_000.0));
         };
+        let now = std::time::Instant::now();
+        let mut last = self.last_energy.lock().unwrap();
+        let power = match *last {
+            Some((then, previous)) if energy >= previous => {
+                let elapsed = now.duration_since(then).as_secs_f64();
+                (elapsed > 0.0).then(|| (energy - previous) as f64 / 1_000_000.0 / elapsed)
+            }
+            _ => None,
+        };
+        *last = Some((now, energy));
+        Ok(power)
+    }
 
-        let fan_speed = self.read_fan_speed();
+    /// Local memory (VRAM) total and available bytes, discrete cards only.
+    fn read_lmem(&self) -> Reading<(u64, u64)> {
+        for (total, avail) in [
+            ("lmem_total_bytes", "lmem_avail_bytes"),
+            ("prelim_lmem_total_bytes", "prelim_lmem_avail_bytes"),
+        ] {
+            let total = read_sysfs_metric(&self.card_path.join(total))?;
+            let avail = read_sysfs_metric(&self.card_path.join(avail))?;
+            if let (Some(total), Some(avail)) = (total, avail) {
+                return Ok(Some((total, avail)));
+            }
+        }
+        Ok(None)
+    }
+}
 
-        let gpu_data = GpuData {
-            timestamp: self.start_time.elapsed().as_secs_f64(),
-            utilization,
-            memory_used,
-            memory_total,
-            temperature,
-            gpu_clock,
-            memory_clock,
-            power_usage,
-            power_limit,
-            fan_speed,
-            // amdgpu sysfs does not expose PCIe throughput counters
-            pcie_throughput_tx: 0.0,
-            pcie_throughput_rx: 0.0,
+impl GpuMonitor for IntelMonitor {
+    fn get_static_info(&self) -> GpuInfo {
+        let name = read_uevent_value(&self.device_path, "PCI_ID")
+            .map(|id| format!("Intel GPU [{id}]"))
+            .unwrap_or_else(|| "Intel GPU".to_string());
+
+        let pcie_width = read_sysfs(&self.device_path.join("current_link_width")).unwrap_or(0);
+        let pcie_gen = std::fs::read_to_string(self.device_path.join("current_link_speed"))
+            .ok()
+            .and_then(|s| parse_pcie_gen(&s))
+            .unwrap_or(0);
+
+        GpuInfo {
+            vendor: GpuVendor::Intel,
+            name,
+            uuid: "N/A".to_string(),
+            bus_id: self.bus_id.clone(),
+            driver_version: self.driver.clone(),
+            vbios_version: "N/A".to_string(),
+            pcie_gen,
+            pcie_width,
+            max_gpu_clock: self.read_gt_freq("max").ok().flatten(),
+        }
+    }
+
+    fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError> {
+        let started = Instant::now();
+        check_present(&self.device_path)?;
+        let mut errors = Vec::new();
+        let mut data = GpuData::empty(&self.bus_id, self.start_time.elapsed().as_secs_f64());
+
+        let lmem = match self.read_lmem() {
+            Ok(lmem) => lmem,
+            Err(message) => {
+                errors.push(MetricError {
+                    metric: Metric::MemoryUsed,
+                    message: message.clone(),
+                });
+                errors.push(MetricError {
+                    metric: Metric::MemoryTotal,
+                    message,
+                });
+                None
+            }
         };
+        if let Some((total, avail)) = lmem {
+            data.memory_used = Some(total.saturating_sub(avail) as f64 / 1024.0 / 1024.0 / 1024.0);
+            data.memory_total = Some(total as f64 / 1024.0 / 1024.0 / 1024.0);
+        }
+
+        data.power_usage = take(&mut errors, Metric::PowerUsage, self.read_power());
+        data.power_limit = take(
+            &mut errors,
+            Metric::PowerLimit,
+            self.hwmon.read::<u64>("power1_max"),
+        )
+        .map(|p| p as f64 / 1_000_000.0);
+
+        data.temperature = take(
+            &mut errors,
+            Metric::Temperature,
+            self.hwmon.temperature(None),
+        )
+        .map(|millidegrees| (millidegrees / 1000) as u32);
+        data.gpu_clock = take(&mut errors, Metric::GpuClock, self.read_gt_freq("cur"));
+        // Memory clock, fan speed and PCIe throughput are not exposed by i915/xe
 
-        // amdgpu_sysfs does not provide per-process GPU usage
-        Ok((gpu_data, Vec::new()))
+        // Engine busyness is only exposed through perf PMU, not sysfs, so
+        // approximate it from the engine time of every DRM client
+        let scan = self.fdinfo.measure();
+        data.utilization = scan.busy_percent;
+        let processes = scan.processes;
+
+        data.errors = errors;
+
+        data.collection_time = Some(elapsed_ms(started));
+        Ok((data, processes))
     }
 }
 
-// ── Factory ─────────────────────────────────────────────────────────────────
+// ── Shared sysfs helpers ────────────────────────────────────────────────────
 
-pub fn create_monitor() -> Option<Box<dyn GpuMonitor>> {
-    // Try NVIDIA first
-    if let Ok(monitor) = NvmlMonitor::new(0) {
-        println!("✅ NVML monitor initialized successfully.");
-        return Some(Box::new(monitor));
+/// Scan `sysfs_root/class/drm/card*/device/` for every device bound to one
+/// of the given kernel drivers, in card order.
+fn find_drm_devices(sysfs_root: &Path, drivers: &[&str]) -> Vec<PathBuf> {
+    let Ok(drm_dir) = std::fs::read_dir(sysfs_root.join("class/drm")) else {
+        return Vec::new();
+    };
+    let mut cards: Vec<_> = drm_dir
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name();
+            let name = name.to_string_lossy();
+            // Match "card0", "card1", ... but not "card0-DP-1" etc.
+            name.starts_with("card") && name[4..].chars().all(|c| c.is_ascii_digit())
+        })
+        .collect();
+    // Sort numerically so that card10 comes after card9
+    cards.sort_by_key(|e| {
+        e.file_name().to_string_lossy()[4..]
+            .parse::<u32>()
+            .unwrap_or(u32::MAX)
+    });
+
+    cards
+        .into_iter()
+        .map(|entry| entry.path().join("device"))
+        .filter(|device_path| {
+            read_uevent_value(device_path, "DRIVER")
+                .is_some_and(|driver| drivers.contains(&driver.as_str()))
+        })
+        .collect()
+}
+
+/// Read a process's short name from `procfs_root/<pid>/comm`.
+pub(crate) fn read_process_name(procfs_root: &Path, pid: u32) -> String {
+    std::fs::read_to_string(procfs_root.join(pid.to_string()).join("comm"))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Parse strings like "8.0 GT/s PCIe" to PCIe generation.
+fn parse_pcie_gen(speed: &str) -> Option<u32> {
+    let rate = speed
+        .split_whitespace()
+        .find_map(|part| part.parse::<f32>().ok())?;
+
+    if rate >= 31.5 {
+        Some(5)
+    } else if rate >= 15.5 {
+        Some(4)
+    } else if rate >= 7.5 {
+        Some(3)
+    } else if rate >= 4.5 {
+        Some(2)
+    } else if rate >= 2.4 {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Read and parse a single-value sysfs attribute.
+fn read_sysfs<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Read a sysfs attribute as a metric. A missing attribute means the device
+/// does not support it; drivers also answer `ENODATA` or `EOPNOTSUPP` for
+/// attributes they create but cannot back on a given card.
+fn read_sysfs_metric<T: std::str::FromStr>(path: &Path) -> Reading<T> {
+    const ENODATA: i32 = 61;
+    const EOPNOTSUPP: i32 = 95;
+
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) if matches!(e.raw_os_error(), Some(ENODATA | EOPNOTSUPP)) => return Ok(None),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    text.trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("{}: unexpected value '{}'", path.display(), text.trim()))
+}
+
+/// The hwmon directories of one DRM device. Some cards expose more than
+/// one, with sensors split between them.
+struct Hwmon {
+    paths: Vec<PathBuf>,
+}
+
+impl Hwmon {
+    fn discover(device_path: &Path) -> Self {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(device_path.join("hwmon"))
+            .map(|dir| dir.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        paths.sort();
+        Self { paths }
+    }
+
+    /// Read attribute `name` from the first hwmon directory that has it.
+    fn read<T: std::str::FromStr>(&self, name: &str) -> Reading<T> {
+        let mut first_error = None;
+        for dir in &self.paths {
+            match read_sysfs_metric(&dir.join(name)) {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(None), Err)
     }
 
-    // Try AMD (amdgpu driver via sysfs)
-    if let Ok(monitor) = AmdgpuMonitor::new() {
-        println!("✅ AMDGPU monitor initialized successfully.");
-        return Some(Box::new(monitor));
+    /// Temperature in millidegrees from the sensor labelled
+    /// `preferred_label`, or else the first sensor present.
+    fn temperature(&self, preferred_label: Option<&str>) -> Reading<u64> {
+        let mut sensors = Vec::new();
+        for dir in &self.paths {
+            for i in 1..=16 {
+                let input = dir.join(format!("temp{i}_input"));
+                if input.exists() {
+                    let label = read_sysfs::<String>(&dir.join(format!("temp{i}_label")));
+                    sensors.push((label, input));
+                }
+            }
+        }
+        let sensor = sensors
+            .iter()
+            .find(|(label, _)| preferred_label.is_some() && label.as_deref() == preferred_label)
+            .or_else(|| sensors.first());
+        match sensor {
+            Some((_, input)) => read_sysfs_metric(input),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Classify a failure to open an amdgpu device like `check_present` does:
+/// unreadable sysfs is a permission problem and a missing one a lost device.
+fn amdgpu_init_error(device_path: &Path, error: amdgpu_sysfs::error::Error) -> MonitorError {
+    use amdgpu_sysfs::error::ErrorKind;
+    match &error.kind {
+        ErrorKind::IoError(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            MonitorError::PermissionDenied(device_path.display().to_string())
+        }
+        ErrorKind::IoError(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            MonitorError::DeviceLost
+        }
+        _ => MonitorError::SamplingFailed(format!("amdgpu_sysfs init: {error}")),
+    }
+}
+
+/// Fail with `DeviceLost` once a device's sysfs directory is gone, e.g.
+/// after an eGPU was unplugged; its attributes would otherwise all read as
+/// unsupported.
+fn check_present(device_path: &Path) -> Result<(), MonitorError> {
+    match std::fs::metadata(device_path.join("uevent")) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Err(
+            MonitorError::PermissionDenied(device_path.display().to_string()),
+        ),
+        Err(_) => Err(MonitorError::DeviceLost),
+    }
+}
+
+/// Read a `KEY=value` entry from a sysfs device's `uevent` file.
+fn read_uevent_value(device_path: &Path, key: &str) -> Option<String> {
+    let uevent = std::fs::read_to_string(device_path.join("uevent")).ok()?;
+    uevent.lines().find_map(|line| {
+        line.split_once('=')
+            .filter(|(k, _)| *k == key)
+            .map(|(_, v)| v.to_string())
+    })
+}
+
+// ── Backend registry ────────────────────────────────────────────────────────
+
+/// Probe function for a backend: returns one monitor per device it drives.
+pub type ProbeFn = fn() -> Result<Vec<Box<dyn GpuMonitor>>, MonitorError>;
+
+/// Where monitors come from: a GPU backend, or the steps of `load_monitors`
+/// that can leave RGM without a device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Nvml,
+    Amdgpu,
+    Intel,
+    DeviceFilter,
+    Replay,
+}
+
+impl Backend {
+    /// Name shown to the user and accepted by `--backend`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Nvml => "NVML",
+            Backend::Amdgpu => "AMDGPU",
+            Backend::Intel => "Intel",
+            Backend::DeviceFilter => "Device filter",
+            Backend::Replay => "Replay",
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A backend that failed to initialise, and why.
+#[derive(Debug)]
+pub struct BackendFailure {
+    pub backend: Backend,
+    pub error: MonitorError,
+}
+
+impl BackendFailure {
+    /// What the user can do about the failure.
+    pub fn remedy(&self) -> &'static str {
+        match (self.backend, &self.error) {
+            (_, MonitorError::LibraryNotFound { .. }) => {
+                "libnvidia-ml.so could not be loaded. Install the NVIDIA driver, or add the \
+                 directory containing the library to LD_LIBRARY_PATH. Ignore this on machines \
+                 without an NVIDIA GPU."
+            }
+            (_, MonitorError::DriverNotLoaded(_)) => {
+                "The NVIDIA kernel module is not loaded. Check `lsmod | grep nvidia` and \
+                 `dmesg`; after a driver update, reboot."
+            }
+            (Backend::Nvml, MonitorError::PermissionDenied(_)) => {
+                "Your user may not open /dev/nvidia*. Add it to the group owning those \
+                 devices, usually video."
+            }
+            (_, MonitorError::PermissionDenied(_)) => {
+                "Your user may not read the GPU's sysfs files. Add it to the video and \
+                 render groups."
+            }
+            (Backend::Nvml, _) => {
+                "Check that `nvidia-smi` works; if it does not, reinstall the NVIDIA driver."
+            }
+            (Backend::Amdgpu, _) => {
+                "No usable card is driven by amdgpu. Check `lsmod | grep amdgpu`, and that \
+                 your user can read /sys/class/drm/card*/device (try adding it to the video \
+                 and render groups). Ignore this on machines without an AMD GPU."
+            }
+            (Backend::Intel, _) => {
+                "No card is driven by i915 or xe. Check `lsmod | grep -e i915 -e xe`. Ignore \
+                 this on machines without an Intel GPU."
+            }
+            (Backend::DeviceFilter, _) => {
+                "Check the --device patterns, or backends.devices in the config file, against \
+                 the bus ids, UUIDs and names of your GPUs."
+            }
+            (Backend::Replay, _) => "Check that the file exists and is an RGM recording.",
+        }
+    }
+}
+
+/// Result of probing every registered backend.
+#[derive(Default)]
+pub struct ProbeReport {
+    pub monitors: Vec<Box<dyn GpuMonitor>>,
+    pub failures: Vec<BackendFailure>,
+}
+
+/// The set of GPU backends RGM knows about. Every backend is probed and all
+/// that succeed are kept, so mixed-vendor systems show every GPU.
+pub struct BackendRegistry {
+    backends: Vec<(Backend, ProbeFn)>,
+}
+
+impl Default for BackendRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Backend::Nvml, || {
+            Ok(NvmlMonitor::enumerate()?
+                .into_iter()
+                .map(|m| Box::new(m) as Box<dyn GpuMonitor>)
+                .collect())
+        });
+        registry.register(Backend::Amdgpu, || {
+            Ok(AmdgpuMonitor::enumerate()?
+                .into_iter()
+                .map(|m| Box::new(m) as Box<dyn GpuMonitor>)
+                .collect())
+        });
+        registry.register(Backend::Intel, || {
+            Ok(IntelMonitor::enumerate()?
+                .into_iter()
+                .map(|m| Box::new(m) as Box<dyn GpuMonitor>)
+                .collect())
+        });
+        registry
+    }
+}
+
+impl BackendRegistry {
+    /// A registry with no backends.
+    pub fn empty() -> Self {
+        Self {
+            backends: Vec::new(),
+        }
     }
 
-    println!("❌ No compatible GPU monitors found.");
-    None
+    pub fn register(&mut self, backend: Backend, probe: ProbeFn) {
+        self.backends.push((backend, probe));
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.backends
+            .iter()
+            .map(|(backend, _)| backend.name())
+            .collect()
+    }
+
+    /// Keep only the backends named in `names`, ignoring case.
+    pub fn retain(&mut self, names: &[String]) {
+        self.backends
+            .retain(|(backend, _)| names.iter().any(|n| n.eq_ignore_ascii_case(backend.name())));
+    }
+
+    pub fn probe(&self) -> ProbeReport {
+        let mut report = ProbeReport::default();
+        for &(backend, probe) in &self.backends {
+            match probe() {
+                Ok(monitors) => report.monitors.extend(monitors),
+                Err(error) => report.failures.push(BackendFailure { backend, error }),
+            }
+        }
+        report
+    }
+}
+
+// ── Factory ─────────────────────────────────────────────────────────────────
+
+/// Create one monitor per physical GPU, across all supported vendors.
+pub fn enumerate_monitors() -> Vec<Box<dyn GpuMonitor>> {
+    BackendRegistry::default().probe().monitors
+}
+
+/// Create a monitor for the first GPU found.
+pub fn create_monitor() -> Option<Box<dyn GpuMonitor>> {
+    enumerate_monitors().into_iter().next()
 }
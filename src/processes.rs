@@ -0,0 +1,320 @@
+// Process table shared by the backends, the GUI and the terminal UI.
+//
+// Backends only know which PIDs use a GPU and how much; `HostProcesses`
+// adds the owner, command line, resident memory and CPU usage from procfs.
+// `SortKey` and `matches_filter` give both front ends the same ordering and
+// filtering.
+use crate::data::ProcessInfo;
+use crate::monitor::PROCFS_ROOT;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Clock ticks per second in `/proc/<pid>/stat`. The kernel always reports
+/// these in USER_HZ, which is 100 on every architecture Linux supports.
+const USER_HZ: f64 = 100.0;
+
+// ── Host details ────────────────────────────────────────────────────────────
+
+/// Fills in the procfs side of `ProcessInfo` entries. CPU usage is the
+/// delta between consecutive calls, so the first call reports 0%.
+pub struct HostProcesses {
+    procfs_root: PathBuf,
+    passwd: PathBuf,
+    /// uid -> user name, read from `passwd` on first use
+    users: Mutex<Option<HashMap<u32, String>>>,
+    /// pid -> (time of last read, cumulative CPU ticks)
+    previous: Mutex<HashMap<u32, (Instant, u64)>>,
+}
+
+impl Default for HostProcesses {
+    fn default() -> Self {
+        Self::new(PROCFS_ROOT)
+    }
+}
+
+impl HostProcesses {
+    pub fn new(procfs_root: impl Into<PathBuf>) -> Self {
+        Self {
+            procfs_root: procfs_root.into(),
+            passwd: PathBuf::from("/etc/passwd"),
+            users: Mutex::new(None),
+            previous: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolve user names against this file instead of `/etc/passwd`.
+    pub fn with_passwd(mut self, passwd: impl Into<PathBuf>) -> Self {
+        self.passwd = passwd.into();
+        self
+    }
+
+    /// Set user, command line, host memory and CPU usage of `processes`.
+    /// Fields that cannot be read, e.g. for other users' processes under
+    /// hardened procfs, are left as they are.
+    pub fn fill(&self, processes: &mut [ProcessInfo]) {
+        let now = Instant::now();
+        let mut previous = self.previous.lock().unwrap();
+        let mut current = HashMap::new();
+
+        for process in processes.iter_mut() {
+            let dir = self.procfs_root.join(process.pid.to_string());
+            if let Ok(status) = std::fs::read_to_string(dir.join("status")) {
+                let (uid, rss) = parse_status(&status);
+                if let Some(uid) = uid {
+                    process.user = self.user_name(uid);
+                }
+                process.host_memory = rss.unwrap_or(process.host_memory);
+            }
+            if let Ok(cmdline) = std::fs::read(dir.join("cmdline")) {
+                process.command_line = parse_cmdline(&cmdline);
+            }
+            let Some(ticks) = std::fs::read_to_string(dir.join("stat"))
+                .ok()
+                .and_then(|stat| parse_cpu_ticks(&stat))
+            else {
+                continue;
+            };
+            if let Some((then, last_ticks)) = previous.get(&process.pid) {
+                let elapsed = now.duration_since(*then).as_secs_f64();
+                if elapsed > 0.0 && ticks >= *last_ticks {
+                    let busy = (ticks - last_ticks) as f64 / USER_HZ;
+                    process.cpu_percent = (busy / elapsed * 100.0) as f32;
+                }
+            }
+            current.insert(process.pid, (now, ticks));
+        }
+
+        // Drop state for processes that have gone away
+        *previous = current;
+    }
+
+    fn user_name(&self, uid: u32) -> String {
+        let mut users = self.users.lock().unwrap();
+        let users = users.get_or_insert_with(|| {
+            std::fs::read_to_string(&self.passwd)
+                .map(|text| parse_passwd(&text))
+                .unwrap_or_default()
+        });
+        users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+}
+
+/// Real uid and resident memory in bytes from `/proc/<pid>/status`.
+pub fn parse_status(text: &str) -> (Option<u32>, Option<u64>) {
+    let mut uid = None;
+    let mut rss = None;
+    for line in text.lines() {
+        if let Some(value) = line.strip_prefix("Uid:") {
+            uid = value.split_whitespace().next().and_then(|v| v.parse().ok());
+        } else if let Some(value) = line.strip_prefix("VmRSS:") {
+            // Always reported in kB
+            rss = value
+                .split_whitespace()
+                .next()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|kb| kb * 1024);
+        }
+    }
+    (uid, rss)
+}
+
+/// User plus system CPU time in clock ticks from `/proc/<pid>/stat`.
+pub fn parse_cpu_ticks(text: &str) -> Option<u64> {
+    // The command name may contain spaces and parentheses, so count fields
+    // from the last ')': state is field 3, utime 14 and stime 15
+    let (_, fields) = text.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// `/proc/<pid>/cmdline` with its NUL separators turned into spaces.
+pub fn parse_cmdline(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// uid -> name from a passwd file.
+pub fn parse_passwd(text: &str) -> HashMap<u32, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Combine entries of the same PID, as when a backend lists graphics and
+/// compute contexts separately. Memory is per process, not per context, so
+/// the larger figure is kept rather than the sum. Sorted by PID.
+pub fn merge_processes(processes: Vec<ProcessInfo>) -> Vec<ProcessInfo> {
+    let mut merged: Vec<ProcessInfo> = Vec::with_capacity(processes.len());
+    for process in processes {
+        match merged.iter_mut().find(|p| p.pid == process.pid) {
+            Some(existing) => {
+                existing.memory_usage = existing.memory_usage.max(process.memory_usage);
+                existing.process_type = match (existing.process_type, process.process_type) {
+                    (Some(a), Some(b)) => Some(a.merge(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            None => merged.push(process),
+        }
+    }
+    merged.sort_by_key(|p| p.pid);
+    merged
+}
+
+// ── Sorting and filtering ───────────────────────────────────────────────────
+
+/// Column the process table is sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Pid,
+    Name,
+    User,
+    Type,
+    GpuMemory,
+    HostMemory,
+    Cpu,
+    Gpu,
+    Sm,
+    Encoder,
+    Decoder,
+    Command,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 12] = [
+        SortKey::Pid,
+        SortKey::Name,
+        SortKey::User,
+        SortKey::Type,
+        SortKey::GpuMemory,
+        SortKey::HostMemory,
+        SortKey::Cpu,
+        SortKey::Gpu,
+        SortKey::Sm,
+        SortKey::Encoder,
+        SortKey::Decoder,
+        SortKey::Command,
+    ];
+
+    /// The key after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Whether the largest values are the interesting ones, so a click on
+    /// the column header sorts descending first.
+    pub fn descending_first(self) -> bool {
+        !matches!(
+            self,
+            SortKey::Pid | SortKey::Name | SortKey::User | SortKey::Type | SortKey::Command
+        )
+    }
+
+    fn compare(self, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+        let text = |a: &str, b: &str| a.to_lowercase().cmp(&b.to_lowercase());
+        // Processes without a value sort below those with one
+        let optional = |a: Option<f32>, b: Option<f32>| match (a, b) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        };
+        match self {
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Name => text(&a.name, &b.name),
+            SortKey::User => text(&a.user, &b.user),
+            SortKey::Type => a.process_type.cmp(&b.process_type),
+            SortKey::GpuMemory => a.memory_usage.cmp(&b.memory_usage),
+            SortKey::HostMemory => a.host_memory.cmp(&b.host_memory),
+            SortKey::Cpu => a.cpu_percent.total_cmp(&b.cpu_percent),
+            SortKey::Gpu => a.gpu_utilization.total_cmp(&b.gpu_utilization),
+            SortKey::Sm => optional(a.sm_utilization, b.sm_utilization),
+            SortKey::Encoder => optional(a.encoder_utilization, b.encoder_utilization),
+            SortKey::Decoder => optional(a.decoder_utilization, b.decoder_utilization),
+            SortKey::Command => text(&a.command_line, &b.command_line),
+        }
+    }
+}
+
+/// How the process table is ordered; saved with the GUI settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Default for ProcessSort {
+    fn default() -> Self {
+        Self {
+            key: SortKey::GpuMemory,
+            descending: true,
+        }
+    }
+}
+
+impl ProcessSort {
+    /// Sort by `key`: reverse the order if it already is the key, else
+    /// start in the key's natural direction.
+    pub fn select(&mut self, key: SortKey) {
+        if self.key == key {
+            self.descending = !self.descending;
+        } else {
+            self.key = key;
+            self.descending = key.descending_first();
+        }
+    }
+
+    /// Sort `processes`. Ties keep PID order so rows do not jump around
+    /// between samples.
+    pub fn apply(&self, processes: &mut [ProcessInfo]) {
+        processes.sort_by(|a, b| {
+            let order = self.key.compare(a, b);
+            let order = if self.descending {
+                order.reverse()
+            } else {
+                order
+            };
+            order.then(a.pid.cmp(&b.pid))
+        });
+    }
+}
+
+/// Whether a process matches the table filter: part of its PID, name, user
+/// or command line, ignoring case. An empty filter matches everything.
+pub fn matches_filter(process: &ProcessInfo, filter: &str) -> bool {
+    let filter = filter.trim().to_lowercase();
+    filter.is_empty()
+        || process.pid.to_string().contains(&filter)
+        || [&process.name, &process.user, &process.command_line]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&filter))
+}
+
+/// The processes matching `filter`, in `sort` order.
+pub fn visible_processes(
+    processes: &[ProcessInfo],
+    filter: &str,
+    sort: ProcessSort,
+) -> Vec<ProcessInfo> {
+    let mut visible: Vec<ProcessInfo> = processes
+        .iter()
+        .filter(|p| matches_filter(p, filter))
+        .cloned()
+        .collect();
+    sort.apply(&mut visible);
+    visible
+}
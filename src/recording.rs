@@ -0,0 +1,484 @@
+// Session recording and replay.
+//
+// A recording is an NDJSON file: the first line is a `RecordingHeader`
+// describing every device, each following line is a `RecordedSample` or,
+// for a GPU attached while recording, a `RecordedDevice`. `ReplayMonitor`
+// plays a recording back through the `GpuMonitor` trait, so the GUI,
+// headless mode and the exporter work unchanged on recorded data.
+use crate::cli::CliOptions;
+use crate::data::{GpuData, GpuInfo, ProcessInfo};
+use crate::monitor::{
+    Backend, BackendFailure, BackendRegistry, GpuMonitor, MonitorError, ProbeReport,
+};
+use crate::sampler::{Probe, RESCAN_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+pub const RECORDING_FORMAT: &str = "rgm-recording";
+/// 2: `RecordedDevice` lines for GPUs attached while recording.
+pub const RECORDING_VERSION: u32 = 2;
+
+// How often a replay is checked for devices attached while recording
+const REPLAY_RESCAN: Duration = Duration::from_millis(100);
+
+#[derive(Error, Debug)]
+pub enum RecordingError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error("not an RGM recording")]
+    NotARecording,
+    #[error("unsupported recording version {0}")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub format: String,
+    pub version: u32,
+    /// Seconds since the Unix epoch when recording started
+    pub started_at: f64,
+    pub devices: Vec<GpuInfo>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedSample {
+    /// Seconds since the Unix epoch
+    pub unix_time: f64,
+    pub sample: GpuData,
+    pub processes: Vec<ProcessInfo>,
+}
+
+/// A GPU attached after recording started, so missing from the header.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedDevice {
+    /// Seconds since the Unix epoch
+    pub unix_time: f64,
+    pub device: GpuInfo,
+}
+
+/// A line of a recording after the header.
+#[derive(Clone, Debug)]
+pub enum RecordedLine {
+    Device(RecordedDevice),
+    Sample(RecordedSample),
+}
+
+impl RecordedLine {
+    pub fn unix_time(&self) -> f64 {
+        match self {
+            RecordedLine::Device(device) => device.unix_time,
+            RecordedLine::Sample(sample) => sample.unix_time,
+        }
+    }
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Appends samples to a recording file.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    /// Create (or truncate) `path` and write the header for `devices`.
+    pub fn create(path: &Path, devices: Vec<GpuInfo>) -> Result<Self, RecordingError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = RecordingHeader {
+            format: RECORDING_FORMAT.to_string(),
+            version: RECORDING_VERSION,
+            started_at: unix_now(),
+            devices,
+        };
+        serde_json::to_writer(&mut writer, &header).map_err(std::io::Error::from)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    pub fn record(
+        &mut self,
+        sample: &GpuData,
+        processes: &[ProcessInfo],
+    ) -> Result<(), RecordingError> {
+        self.write_line(&RecordedSample {
+            unix_time: unix_now(),
+            sample: sample.clone(),
+            processes: processes.to_vec(),
+        })
+    }
+
+    /// Describe a GPU attached after the header was written, before its
+    /// first sample.
+    pub fn add_device(&mut self, device: &GpuInfo) -> Result<(), RecordingError> {
+        self.write_line(&RecordedDevice {
+            unix_time: unix_now(),
+            device: device.clone(),
+        })
+    }
+
+    fn write_line(&mut self, entry: &impl Serialize) -> Result<(), RecordingError> {
+        serde_json::to_writer(&mut self.writer, entry).map_err(std::io::Error::from)?;
+        writeln!(self.writer)?;
+        // Flush every line so a crash or kill loses at most one
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// An open recording. Samples are read as playback reaches them, not loaded
+/// up front, so replaying a long session takes little memory.
+pub struct Recording {
+    pub header: RecordingHeader,
+    lines: Lines<BufReader<File>>,
+    /// Number of the next line, for parse errors
+    line: usize,
+}
+
+impl Recording {
+    /// Open `path` and check its header.
+    pub fn open(path: &Path) -> Result<Self, RecordingError> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let first = lines.next().ok_or(RecordingError::NotARecording)??;
+        let header: RecordingHeader =
+            serde_json::from_str(&first).map_err(|_| RecordingError::NotARecording)?;
+        if header.format != RECORDING_FORMAT {
+            return Err(RecordingError::NotARecording);
+        }
+        if !(1..=RECORDING_VERSION).contains(&header.version) {
+            return Err(RecordingError::UnsupportedVersion(header.version));
+        }
+
+        Ok(Self {
+            header,
+            lines,
+            line: 2,
+        })
+    }
+
+    /// The next sample or attached device in the file, or `None` at its end.
+    pub fn next_line(&mut self) -> Option<Result<RecordedLine, RecordingError>> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let number = self.line;
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str::<serde_json::Value>(&line).and_then(|entry| {
+                if entry.get("device").is_some() {
+                    serde_json::from_value(entry).map(RecordedLine::Device)
+                } else {
+                    serde_json::from_value(entry).map(RecordedLine::Sample)
+                }
+            });
+            return Some(entry.map_err(|source| RecordingError::Parse {
+                line: number,
+                source,
+            }));
+        }
+    }
+
+    /// One replay monitor per device in the header, sharing a playback
+    /// clock. `speed` scales playback: 1.0 is real time, 10.0 is ten times
+    /// faster. Devices attached while recording are left out.
+    pub fn into_monitors(self, speed: f64) -> Vec<ReplayMonitor> {
+        self.into_replay(speed).0
+    }
+
+    /// Like `into_monitors`, and where to take monitors for the devices
+    /// attached while recording from.
+    pub fn into_replay(mut self, speed: f64) -> (Vec<ReplayMonitor>, AttachedDevices) {
+        let pending = self.next_line();
+        let origin = match &pending {
+            Some(Ok(first)) => first.unix_time(),
+            _ => self.header.started_at,
+        };
+        let devices = self.header.devices.clone();
+        let playback = Arc::new(Mutex::new(Playback {
+            queued: devices
+                .iter()
+                .map(|info| (info.bus_id.clone(), VecDeque::new()))
+                .collect(),
+            attached: Some(Vec::new()),
+            recording: self,
+            pending,
+            origin,
+            speed,
+            start: Instant::now(),
+        }));
+
+        let monitors = devices
+            .into_iter()
+            .map(|info| ReplayMonitor::new(info, &playback))
+            .collect();
+        (monitors, AttachedDevices(playback))
+    }
+}
+
+// Playback of one recording, shared by the monitors of its devices
+struct Playback {
+    recording: Recording,
+    /// Read from the file but not due yet
+    pending: Option<Result<RecordedLine, RecordingError>>,
+    /// Due samples each device has not taken yet, by bus id
+    queued: HashMap<String, VecDeque<RecordedSample>>,
+    /// Attached devices not replayed yet; `None` once nobody takes them
+    attached: Option<Vec<GpuInfo>>,
+    /// Wall-clock time of the first sample in the recording
+    origin: f64,
+    speed: f64,
+    start: Instant,
+}
+
+impl Playback {
+    /// Read every line that is due: queue samples for their device, and
+    /// start queueing for devices attached while recording.
+    fn advance(&mut self) -> Result<(), RecordingError> {
+        let position = self.origin + self.start.elapsed().as_secs_f64() * self.speed;
+        loop {
+            let Some(next) = self.pending.take().or_else(|| self.recording.next_line()) else {
+                return Ok(());
+            };
+            let line = next?;
+            if line.unix_time() > position {
+                self.pending = Some(Ok(line));
+                return Ok(());
+            }
+            match line {
+                RecordedLine::Device(RecordedDevice { device, .. }) => {
+                    if let Some(attached) = &mut self.attached {
+                        if !self.queued.contains_key(&device.bus_id) {
+                            self.queued.insert(device.bus_id.clone(), VecDeque::new());
+                            attached.push(device);
+                        }
+                    }
+                }
+                // Devices never described, or no longer replayed, are
+                // dropped
+                RecordedLine::Sample(sample) => {
+                    if let Some(queue) = self.queued.get_mut(&sample.sample.device_id) {
+                        queue.push_back(sample);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Hands out a replay monitor for each device attached while recording,
+/// once its `RecordedDevice` line is reached.
+pub struct AttachedDevices(Arc<Mutex<Playback>>);
+
+impl AttachedDevices {
+    /// Monitors for the devices attached since the last call.
+    pub fn take(&self) -> Result<Vec<ReplayMonitor>, RecordingError> {
+        let mut playback = self.0.lock().unwrap();
+        playback.advance()?;
+        let attached = playback.attached.as_mut().map(std::mem::take);
+        Ok(attached
+            .unwrap_or_default()
+            .into_iter()
+            .map(|info| ReplayMonitor::new(info, &self.0))
+            .collect())
+    }
+}
+
+impl Drop for AttachedDevices {
+    fn drop(&mut self) {
+        // Stop queueing samples for devices nobody will replay
+        if let Ok(mut playback) = self.0.lock() {
+            for info in playback.attached.take().unwrap_or_default() {
+                playback.queued.remove(&info.bus_id);
+            }
+        }
+    }
+}
+
+/// Plays back one device of a recording.
+pub struct ReplayMonitor {
+    info: GpuInfo,
+    playback: Arc<Mutex<Playback>>,
+    /// The last sample returned, repeated until the next one is due
+    latest: Mutex<Option<RecordedSample>>,
+}
+
+impl ReplayMonitor {
+    fn new(info: GpuInfo, playback: &Arc<Mutex<Playback>>) -> Self {
+        Self {
+            info,
+            playback: Arc::clone(playback),
+            latest: Mutex::new(None),
+        }
+    }
+}
+
+impl GpuMonitor for ReplayMonitor {
+    fn get_static_info(&self) -> GpuInfo {
+        self.info.clone()
+    }
+
+    /// Return the latest sample whose recorded time has been reached. Once
+    /// the recording ends, the final sample keeps being returned.
+    fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError> {
+        let mut batch = self.sample_batch()?;
+        Ok(batch.pop().expect("a batch holds at least one sample"))
+    }
+
+    /// Every sample that became due since the previous call, so a sped up
+    /// replay still shows short spikes.
+    fn sample_batch(&self) -> Result<Vec<(GpuData, Vec<ProcessInfo>)>, MonitorError> {
+        let due: Vec<RecordedSample> = {
+            let mut playback = self.playback.lock().unwrap();
+            playback
+                .advance()
+                .map_err(|e| MonitorError::Recording(e.to_string()))?;
+            playback
+                .queued
+                .get_mut(&self.info.bus_id)
+                .map(|queue| queue.drain(..).collect())
+                .unwrap_or_default()
+        };
+
+        let mut latest = self.latest.lock().unwrap();
+        if let Some(last) = due.last() {
+            *latest = Some(last.clone());
+        }
+        let due = if due.is_empty() {
+            let entry = latest
+                .clone()
+                .ok_or_else(|| MonitorError::Recording("no samples for this device".into()))?;
+            vec![entry]
+        } else {
+            due
+        };
+        Ok(due
+            .into_iter()
+            .map(|entry| (entry.sample, entry.processes))
+            .collect())
+    }
+}
+
+impl Drop for ReplayMonitor {
+    fn drop(&mut self) {
+        // Stop queueing samples nobody will take
+        if let Ok(mut playback) = self.playback.lock() {
+            playback.queued.remove(&self.info.bus_id);
+        }
+    }
+}
+
+/// The monitors selected by the command line: a replayed recording if
+/// `--replay` was given, otherwise every GPU the `--backend` backends can
+/// find, narrowed down to the `--device` matches. A recording that cannot be
+/// loaded is reported as a failed "Replay" backend.
+pub fn load_monitors(options: &CliOptions) -> ProbeReport {
+    load_supervised(options).0
+}
+
+/// Like `load_monitors`, with the probe that keeps the devices up to date
+/// through `Sampler::supervise` or `DeviceSet::supervise`, and how often to
+/// call it. Live GPUs are detected again; a replay yields the devices
+/// attached while recording as playback reaches them.
+pub fn load_supervised(options: &CliOptions) -> (ProbeReport, Probe, Duration) {
+    match &options.replay {
+        Some(path) => {
+            let (report, attached) = load_replay(path, options.speed);
+            let devices = options.devices.clone();
+            let probe = move || {
+                let Some(attached) = &attached else {
+                    return Vec::new();
+                };
+                let monitors = attached.take().unwrap_or_else(|e| {
+                    log::warn!("replay: {e}");
+                    Vec::new()
+                });
+                monitors
+                    .into_iter()
+                    .filter(|monitor| selected(&devices, &monitor.get_static_info()))
+                    .map(|monitor| Box::new(monitor) as Box<dyn GpuMonitor>)
+                    .collect()
+            };
+            (
+                select_devices(options, report),
+                Box::new(probe),
+                REPLAY_RESCAN,
+            )
+        }
+        None => {
+            let mut registry = BackendRegistry::default();
+            if !options.backends.is_empty() {
+                registry.retain(&options.backends);
+            }
+            let report = select_devices(options, registry.probe());
+            let options = options.clone();
+            let probe = move || load_monitors(&options).monitors;
+            (report, Box::new(probe), RESCAN_INTERVAL)
+        }
+    }
+}
+
+// Whether `--device` patterns select a device; no pattern selects all
+fn selected(patterns: &[String], info: &GpuInfo) -> bool {
+    patterns.is_empty() || patterns.iter().any(|pattern| info.matches(pattern))
+}
+
+// Narrow a probe down to the `--device` matches
+fn select_devices(options: &CliOptions, mut report: ProbeReport) -> ProbeReport {
+    if !options.devices.is_empty() && !report.monitors.is_empty() {
+        report
+            .monitors
+            .retain(|monitor| selected(&options.devices, &monitor.get_static_info()));
+        if report.monitors.is_empty() {
+            report.failures.push(BackendFailure {
+                backend: Backend::DeviceFilter,
+                error: MonitorError::NoMatchingDevice(options.devices.join(", ")),
+            });
+        }
+    }
+    report
+}
+
+fn load_replay(path: &Path, speed: f64) -> (ProbeReport, Option<AttachedDevices>) {
+    match Recording::open(path) {
+        Ok(recording) => {
+            let (monitors, attached) = recording.into_replay(speed);
+            let report = ProbeReport {
+                monitors: monitors
+                    .into_iter()
+                    .map(|m| Box::new(m) as Box<dyn GpuMonitor>)
+                    .collect(),
+                failures: Vec::new(),
+            };
+            (report, Some(attached))
+        }
+        Err(e) => {
+            let report = ProbeReport {
+                monitors: Vec::new(),
+                failures: vec![BackendFailure {
+                    backend: Backend::Replay,
+                    error: MonitorError::Recording(format!("{}: {e}", path.display())),
+                }],
+            };
+            (report, None)
+        }
+    }
+}
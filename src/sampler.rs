@@ -0,0 +1,683 @@
+// Background sampling shared by the GUI and the terminal UI.
+//
+// One thread per device samples its monitor, records, logs to CSV and
+// evaluates alerts, then passes the sample to the UI thread, where
+// `Sampler::update` adds it to the device's history. Both front ends
+// therefore show exactly the same data.
+//
+// A device whose samples keep failing is marked offline and no longer
+// sampled. `Sampler::supervise` then probes the backends again, with backoff,
+// and hands the device a fresh monitor once it reappears, e.g. after an NVML
+// reset or an eGPU is plugged back in. The same probes pick up GPUs attached
+// after startup. Headless mode and the exporter, which sample on a single
+// thread, get the same handling from `DeviceSet`.
+use crate::alerts::AlertEngine;
+use crate::csv_log::CsvWriter;
+use crate::data::{GpuData, GpuInfo, ProcessInfo};
+use crate::history::History;
+use crate::monitor::{GpuMonitor, MonitorError};
+use crate::recording::Recorder;
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender, TrySendError};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Static info, sample history and latest process list of one device.
+pub struct DeviceState {
+    pub gpu_info: GpuInfo,
+    pub history: History,
+    pub processes: Vec<ProcessInfo>,
+    pub status: DeviceStatus,
+}
+
+/// Whether a device is currently delivering samples.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceStatus {
+    #[default]
+    Online,
+    /// Sampling keeps failing, e.g. after a driver reset or an unplug; with
+    /// the error that took it offline
+    Offline(String),
+}
+
+/// Samples that may queue up between two `Sampler::update` calls, e.g.
+/// while the window is hidden; further samples are dropped rather than
+/// stalling recording, CSV logging and alerts.
+const QUEUE_CAPACITY: usize = 1000;
+
+/// Consecutive failed samples after which a device is marked offline.
+const OFFLINE_AFTER: u32 = 3;
+
+/// Delay before probing for an offline device again, doubled after every
+/// attempt that does not bring it back, up to `MAX_BACKOFF`.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often the front ends look for newly attached GPUs.
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+// What the sampling threads tell the UI thread; dropped when it falls behind
+enum Event {
+    Sample(GpuData, Vec<ProcessInfo>),
+    Error(ReportedError),
+}
+
+// Device changes, rare enough to always be delivered, on a channel of their own
+enum StatusEvent {
+    Added(GpuInfo),
+    Offline { device_id: String, error: String },
+    Online { device_id: String },
+}
+
+/// An error a sampling thread ran into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReportedError {
+    pub device_id: String,
+    /// `MonitorError::kind`, or "recording" or "CSV log"
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl ReportedError {
+    fn new(device_id: &str, error: &MonitorError) -> Self {
+        Self {
+            device_id: device_id.to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ReportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GPU {}: {}", self.device_id, self.message)
+    }
+}
+
+/// The errors reported since startup, summarized for the status bars.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorLog {
+    /// Number of errors of each kind
+    pub counts: BTreeMap<&'static str, u64>,
+    pub last: Option<ReportedError>,
+}
+
+impl ErrorLog {
+    pub fn report(&mut self, error: ReportedError) {
+        *self.counts.entry(error.kind).or_default() += 1;
+        self.last = Some(error);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// E.g. `3 errors (2 metric read, 1 device lost)`.
+    pub fn summary(&self) -> String {
+        let total = self.total();
+        let kinds: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(kind, count)| format!("{count} {kind}"))
+            .collect();
+        let noun = if total == 1 { "error" } else { "errors" };
+        format!("{total} {noun} ({})", kinds.join(", "))
+    }
+}
+
+// A device as seen by its sampling thread and the supervisor
+#[derive(Default)]
+struct Slot {
+    offline: bool,
+    /// A freshly initialized monitor for the device, picked up by its thread
+    /// on the next tick
+    replacement: Option<Box<dyn GpuMonitor>>,
+}
+
+// Everything a sampling thread needs, cloned into each of them
+#[derive(Clone)]
+struct Shared {
+    sender: Sender<Event>,
+    status: Sender<StatusEvent>,
+    recorder: Arc<Mutex<Option<Recorder>>>,
+    csv_log: Arc<Mutex<Option<CsvWriter>>>,
+    alerts: Arc<Mutex<AlertEngine>>,
+    interval_ms: Arc<AtomicU64>,
+    /// Keyed by bus id
+    slots: Arc<Mutex<HashMap<String, Slot>>>,
+    /// Cleared when the sampler is dropped, to end the threads
+    running: Arc<AtomicBool>,
+}
+
+impl Shared {
+    /// Register a device, unless some thread already samples it. Returns
+    /// whether the caller should start sampling it.
+    fn claim(&self, device_id: &str) -> bool {
+        let mut slots = self.slots.lock().unwrap();
+        if slots.contains_key(device_id) {
+            return false;
+        }
+        slots.insert(device_id.to_string(), Slot::default());
+        true
+    }
+}
+
+pub struct Sampler {
+    pub devices: Vec<DeviceState>,
+    /// Evaluated by the sampling threads, so alerts keep firing while the
+    /// UI is hidden
+    pub alerts: Arc<Mutex<AlertEngine>>,
+    /// CSV logging, shared with the sampling threads; `None` when stopped
+    pub csv_log: Arc<Mutex<Option<CsvWriter>>>,
+    /// Session recording, shared with the sampling threads
+    pub recorder: Arc<Mutex<Option<Recorder>>>,
+    /// Sampling, per-metric, recording and CSV errors received so far
+    pub errors: ErrorLog,
+    shared: Shared,
+    receiver: Receiver<Event>,
+    status: Receiver<StatusEvent>,
+    /// History window of new devices; see `update`
+    window: Duration,
+}
+
+impl Sampler {
+    /// Start one sampling thread per monitor, so a slow backend cannot
+    /// stall the others. `monitors` may be empty; see `add`.
+    pub fn start(
+        monitors: Vec<Box<dyn GpuMonitor>>,
+        interval: Duration,
+        history: Duration,
+        recorder: Option<Recorder>,
+        csv_log: Option<CsvWriter>,
+        alerts: AlertEngine,
+    ) -> Self {
+        let (sender, receiver) = bounded(QUEUE_CAPACITY);
+        let (status_sender, status) = unbounded();
+        let shared = Shared {
+            sender,
+            status: status_sender,
+            recorder: Arc::new(Mutex::new(recorder)),
+            csv_log: Arc::new(Mutex::new(csv_log)),
+            alerts: Arc::new(Mutex::new(alerts)),
+            interval_ms: Arc::new(AtomicU64::new(interval.as_millis() as u64)),
+            slots: Arc::default(),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        let mut sampler = Self {
+            devices: Vec::with_capacity(monitors.len()),
+            alerts: Arc::clone(&shared.alerts),
+            csv_log: Arc::clone(&shared.csv_log),
+            recorder: Arc::clone(&shared.recorder),
+            errors: ErrorLog::default(),
+            shared,
+            receiver,
+            status,
+            window: history,
+        };
+        for monitor in monitors {
+            sampler.add(monitor);
+        }
+        sampler
+    }
+
+    /// Start sampling one more device. A monitor for a device that is
+    /// already sampled is dropped.
+    pub fn add(&mut self, monitor: Box<dyn GpuMonitor>) {
+        let gpu_info = monitor.get_static_info();
+        if !self.shared.claim(&gpu_info.bus_id) {
+            return;
+        }
+        self.devices.push(DeviceState {
+            gpu_info,
+            history: History::new(self.window),
+            processes: Vec::new(),
+            status: DeviceStatus::Online,
+        });
+        spawn_sampling(&self.shared, monitor);
+    }
+
+    /// Keep the devices connected: every `rescan` (or sooner, backing off,
+    /// while a device is offline) call `probe` for fresh monitors. Offline
+    /// devices switch to their new monitor; devices not seen before are
+    /// added.
+    pub fn supervise<F>(&self, probe: F, rescan: Duration)
+    where
+        F: Fn() -> Vec<Box<dyn GpuMonitor>> + Send + 'static,
+    {
+        let shared = self.shared.clone();
+        thread::spawn(move || {
+            let mut schedule = ProbeSchedule::new(rescan);
+            while shared.running.load(Ordering::Relaxed) {
+                thread::sleep(rescan.min(Duration::from_millis(100)));
+                let offline = shared.slots.lock().unwrap().values().any(|s| s.offline);
+                if !schedule.due(offline) {
+                    continue;
+                }
+
+                for monitor in probe() {
+                    let gpu_info = monitor.get_static_info();
+                    if !shared.claim(&gpu_info.bus_id) {
+                        // Devices still sampling fine keep their monitor
+                        let mut slots = shared.slots.lock().unwrap();
+                        if let Some(slot) = slots.get_mut(&gpu_info.bus_id) {
+                            if slot.offline {
+                                slot.replacement = Some(monitor);
+                            }
+                        }
+                        continue;
+                    }
+                    // Described first, so replays know the device of its
+                    // samples
+                    if let Some(recorder) = shared.recorder.lock().unwrap().as_mut() {
+                        if let Err(e) = recorder.add_device(&gpu_info) {
+                            log::warn!("recording: {e}");
+                            let error = ReportedError {
+                                device_id: gpu_info.bus_id.clone(),
+                                kind: "recording",
+                                message: e.to_string(),
+                            };
+                            send(&shared, Event::Error(error));
+                        }
+                    }
+                    // Announced first, so its first sample has a device
+                    if shared.status.send(StatusEvent::Added(gpu_info)).is_err() {
+                        return;
+                    }
+                    spawn_sampling(&shared, monitor);
+                }
+            }
+        });
+    }
+
+    /// The interval the sampling threads currently wait between samples.
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.shared.interval_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        self.shared
+            .interval_ms
+            .store(interval.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Add the samples received since the last call to the device
+    /// histories, which keep `window` worth of data. Returns whether any
+    /// sample or device change arrived.
+    pub fn update(&mut self, window: Duration) -> bool {
+        self.window = window;
+        let mut received = self.update_status();
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                Event::Sample(gpu_data, proc_infos) => {
+                    // The device may have been announced after the statuses
+                    // were read above
+                    if self.device_index(&gpu_data.device_id).is_none() {
+                        self.update_status();
+                    }
+                    let Some(device) = self
+                        .devices
+                        .iter_mut()
+                        .find(|d| d.gpu_info.bus_id == gpu_data.device_id)
+                    else {
+                        continue;
+                    };
+                    for error in &gpu_data.errors {
+                        let error = MonitorError::from(error.clone());
+                        self.errors
+                            .report(ReportedError::new(&gpu_data.device_id, &error));
+                    }
+                    device.history.set_window(window);
+                    device.history.push(gpu_data);
+                    // Samples taken before the device went offline may
+                    // still be queued
+                    if device.status == DeviceStatus::Online {
+                        device.processes = proc_infos;
+                    }
+                }
+                Event::Error(error) => self.errors.report(error),
+            }
+            received = true;
+        }
+        received
+    }
+
+    // Apply the device changes received so far; returns whether there were any
+    fn update_status(&mut self) -> bool {
+        let mut received = false;
+        while let Ok(event) = self.status.try_recv() {
+            match event {
+                StatusEvent::Added(gpu_info) => {
+                    if self.device_index(&gpu_info.bus_id).is_none() {
+                        self.devices.push(DeviceState {
+                            gpu_info,
+                            history: History::new(self.window),
+                            processes: Vec::new(),
+                            status: DeviceStatus::Online,
+                        });
+                    }
+                }
+                StatusEvent::Offline { device_id, error } => {
+                    if let Some(index) = self.device_index(&device_id) {
+                        let device = &mut self.devices[index];
+                        device.processes.clear();
+                        device.status = DeviceStatus::Offline(error);
+                    }
+                }
+                StatusEvent::Online { device_id } => {
+                    if let Some(index) = self.device_index(&device_id) {
+                        self.devices[index].status = DeviceStatus::Online;
+                    }
+                }
+            }
+            received = true;
+        }
+        received
+    }
+
+    /// Index of the device with this bus id.
+    pub fn device_index(&self, device_id: &str) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|d| d.gpu_info.bus_id == device_id)
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Start the sampling thread of a monitor's device, claimed beforehand.
+fn spawn_sampling(shared: &Shared, monitor: Box<dyn GpuMonitor>) {
+    let gpu_info = monitor.get_static_info();
+    let device_id = gpu_info.bus_id.clone();
+    let shared = shared.clone();
+    thread::spawn(move || {
+        let mut link = DeviceLink::new(monitor);
+        while shared.running.load(Ordering::Relaxed) {
+            let replacement = shared
+                .slots
+                .lock()
+                .unwrap()
+                .get_mut(&device_id)
+                .and_then(|slot| slot.replacement.take());
+            if let Some(replacement) = replacement {
+                link.replace(replacement);
+            }
+
+            let was_offline = link.is_offline();
+            match link.sample() {
+                // Retrying is left to the supervisor and its backoff
+                None => {}
+                Some(Ok(batch)) => {
+                    if was_offline {
+                        log::info!("GPU {device_id} is back online");
+                        if let Some(slot) = shared.slots.lock().unwrap().get_mut(&device_id) {
+                            slot.offline = false;
+                        }
+                        let event = StatusEvent::Online {
+                            device_id: device_id.clone(),
+                        };
+                        if shared.status.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    for (gpu_data, proc_infos) in batch {
+                        if !deliver(&shared, &gpu_info, gpu_data, proc_infos) {
+                            return;
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    log::debug!("GPU {device_id}: {e}");
+                    if !send(&shared, Event::Error(ReportedError::new(&device_id, &e))) {
+                        break;
+                    }
+                    // A single failure is often transient; report the device
+                    // once it keeps failing, and not again on every tick
+                    if !was_offline && link.is_offline() {
+                        log::warn!("GPU {device_id} is offline: {e}");
+                        if let Some(slot) = shared.slots.lock().unwrap().get_mut(&device_id) {
+                            slot.offline = true;
+                        }
+                        let event = StatusEvent::Offline {
+                            device_id: device_id.clone(),
+                            error: e.to_string(),
+                        };
+                        if shared.status.send(event).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            thread::sleep(Duration::from_millis(
+                shared.interval_ms.load(Ordering::Relaxed),
+            ));
+        }
+    });
+}
+
+/// Record, log and check alerts on one sample, then pass it to the UI
+/// thread. Returns false once the sampler is gone.
+fn deliver(
+    shared: &Shared,
+    gpu_info: &GpuInfo,
+    gpu_data: GpuData,
+    proc_infos: Vec<ProcessInfo>,
+) -> bool {
+    let device_id = &gpu_info.bus_id;
+    for error in &gpu_data.errors {
+        log::debug!(
+            "GPU {device_id}: {} read failed: {}",
+            error.metric,
+            error.message
+        );
+    }
+    let mut output_errors = Vec::new();
+    if let Some(recorder) = shared.recorder.lock().unwrap().as_mut() {
+        if let Err(e) = recorder.record(&gpu_data, &proc_infos) {
+            output_errors.push(("recording", e.to_string()));
+        }
+    }
+    if let Some(csv) = shared.csv_log.lock().unwrap().as_mut() {
+        if let Err(e) = csv.write(gpu_info, &gpu_data) {
+            output_errors.push(("CSV log", e.to_string()));
+        }
+    }
+    for (kind, message) in output_errors {
+        log::warn!("{kind}: {message}");
+        let error = ReportedError {
+            device_id: device_id.clone(),
+            kind,
+            message,
+        };
+        if !send(shared, Event::Error(error)) {
+            return false;
+        }
+    }
+    shared.alerts.lock().unwrap().process(gpu_info, &gpu_data);
+    send(shared, Event::Sample(gpu_data, proc_infos))
+}
+
+/// Pass an event to the UI thread without waiting for it, dropping the
+/// event when the queue is full. Returns false once the sampler is gone.
+fn send(shared: &Shared, event: Event) -> bool {
+    match shared.sender.try_send(event) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            log::debug!("UI is not keeping up; event dropped");
+            true
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    }
+}
+
+// ── Reconnection ────────────────────────────────────────────────────────────
+
+/// When to probe the backends again: every `rescan`, or while a device is
+/// offline, after a backoff doubling from `MIN_BACKOFF` up to `MAX_BACKOFF`.
+pub struct ProbeSchedule {
+    rescan: Duration,
+    backoff: Duration,
+    last_probe: Instant,
+}
+
+impl ProbeSchedule {
+    pub fn new(rescan: Duration) -> Self {
+        Self {
+            rescan,
+            backoff: MIN_BACKOFF,
+            last_probe: Instant::now(),
+        }
+    }
+
+    /// Whether to probe now, given whether any device is offline. A `true`
+    /// counts as a probe.
+    pub fn due(&mut self, offline: bool) -> bool {
+        if !offline {
+            self.backoff = MIN_BACKOFF;
+        }
+        let wait = if offline { self.backoff } else { self.rescan };
+        if self.last_probe.elapsed() < wait {
+            return false;
+        }
+        self.last_probe = Instant::now();
+        if offline {
+            self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        }
+        true
+    }
+}
+
+// Samples from `GpuMonitor::sample_batch`, oldest first
+type Batch = Vec<(GpuData, Vec<ProcessInfo>)>;
+
+/// A device's monitor as seen by whoever samples it. The device is offline
+/// once `OFFLINE_AFTER` samples in a row failed, and is not sampled again
+/// until it gets a replacement monitor, whose clock starts over; its
+/// timestamps are shifted to carry on from the last sample.
+pub struct DeviceLink {
+    monitor: Box<dyn GpuMonitor>,
+    failures: u32,
+    offset: f64,
+    /// Timestamp of the last sample, and when it was taken
+    last: Option<(f64, Instant)>,
+    rebase: bool,
+}
+
+impl DeviceLink {
+    pub fn new(monitor: Box<dyn GpuMonitor>) -> Self {
+        Self {
+            monitor,
+            failures: 0,
+            offset: 0.0,
+            last: None,
+            rebase: false,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.failures >= OFFLINE_AFTER
+    }
+
+    /// Switch to a freshly initialized monitor for the same device.
+    pub fn replace(&mut self, monitor: Box<dyn GpuMonitor>) {
+        self.monitor = monitor;
+        self.rebase = true;
+    }
+
+    /// The samples taken since the previous call, or `None` while the
+    /// device is offline and waits for a replacement.
+    pub fn sample(&mut self) -> Option<Result<Batch, MonitorError>> {
+        if self.is_offline() && !self.rebase {
+            return None;
+        }
+        let mut batch = match self.monitor.sample_batch() {
+            Ok(batch) => batch,
+            Err(e) => {
+                self.failures = self.failures.saturating_add(1);
+                return Some(Err(e));
+            }
+        };
+        self.failures = 0;
+        if std::mem::take(&mut self.rebase) {
+            if let (Some((timestamp, at)), Some((first, _))) = (self.last, batch.first()) {
+                self.offset = timestamp + at.elapsed().as_secs_f64() - first.timestamp;
+            }
+        }
+        for (gpu_data, _) in &mut batch {
+            gpu_data.timestamp += self.offset;
+            self.last = Some((gpu_data.timestamp, Instant::now()));
+        }
+        Some(Ok(batch))
+    }
+}
+
+/// The devices of the modes that sample on a single thread (headless and
+/// the exporter), kept connected like `Sampler::supervise` does.
+pub struct DeviceSet {
+    pub devices: Vec<(GpuInfo, DeviceLink)>,
+    probe: Option<(Probe, ProbeSchedule)>,
+}
+
+/// Where `supervise` takes fresh monitors from.
+pub type Probe = Box<dyn Fn() -> Vec<Box<dyn GpuMonitor>> + Send>;
+
+impl DeviceSet {
+    pub fn new(monitors: Vec<Box<dyn GpuMonitor>>) -> Self {
+        let devices = monitors
+            .into_iter()
+            .map(|monitor| (monitor.get_static_info(), DeviceLink::new(monitor)))
+            .collect();
+        Self {
+            devices,
+            probe: None,
+        }
+    }
+
+    /// Call `probe` for fresh monitors from `reconnect`, when due.
+    pub fn supervise<F>(&mut self, probe: F, rescan: Duration)
+    where
+        F: Fn() -> Vec<Box<dyn GpuMonitor>> + Send + 'static,
+    {
+        self.probe = Some((Box::new(probe), ProbeSchedule::new(rescan)));
+    }
+
+    /// Probe if due: offline devices switch to their new monitor, and
+    /// devices not seen before are appended. Returns how many were added.
+    pub fn reconnect(&mut self) -> usize {
+        let Some((probe, schedule)) = &mut self.probe else {
+            return 0;
+        };
+        let offline = self.devices.iter().any(|(_, link)| link.is_offline());
+        if !schedule.due(offline) {
+            return 0;
+        }
+
+        let mut added = 0;
+        for monitor in probe() {
+            let gpu_info = monitor.get_static_info();
+            match self
+                .devices
+                .iter_mut()
+                .find(|(info, _)| info.bus_id == gpu_info.bus_id)
+            {
+                // Devices still sampling fine keep their monitor
+                Some((_, link)) => {
+                    if link.is_offline() {
+                        link.replace(monitor);
+                    }
+                }
+                None => {
+                    log::info!("GPU {} attached", gpu_info.bus_id);
+                    self.devices.push((gpu_info, DeviceLink::new(monitor)));
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+}
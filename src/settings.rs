@@ -0,0 +1,73 @@
+// GUI settings, persisted between runs through eframe's storage
+use crate::cli::CliOptions;
+use crate::processes::ProcessSort;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+pub const MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+pub const MAX_SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+pub const MIN_HISTORY: Duration = Duration::from_secs(10);
+pub const MAX_HISTORY: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Time between two samples of the same device
+    pub sample_interval: Duration,
+    /// How far back the plots reach
+    pub history: Duration,
+    /// Ids of the metric plots switched off in the UI
+    pub hidden_plots: BTreeSet<String>,
+    /// Column and direction of the process table
+    pub process_sort: ProcessSort,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            sample_interval: Duration::from_millis(100),
+            history: Duration::from_secs(10),
+            hidden_plots: BTreeSet::new(),
+            process_sort: ProcessSort::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Key under which the settings are stored by eframe.
+    pub const STORAGE_KEY: &'static str = "settings";
+
+    /// Apply `--interval` and `--history`, which take precedence over the
+    /// stored settings, and clamp everything to the supported ranges.
+    pub fn with_cli(mut self, options: &CliOptions) -> Self {
+        if let Some(interval) = options.interval {
+            self.sample_interval = interval;
+        }
+        if let Some(history) = options.history {
+            self.history = history;
+        }
+        self.clamped()
+    }
+
+    /// What to store in place of `stored`: the sampling interval and
+    /// history of `stored`, which leave out command-line and config file
+    /// overrides, and everything else as currently shown.
+    pub fn persisted(&self, stored: &Settings) -> Self {
+        Self {
+            sample_interval: stored.sample_interval,
+            history: stored.history,
+            ..self.clone()
+        }
+    }
+
+    pub fn clamped(self) -> Self {
+        Self {
+            sample_interval: self
+                .sample_interval
+                .clamp(MIN_SAMPLE_INTERVAL, MAX_SAMPLE_INTERVAL),
+            history: self.history.clamp(MIN_HISTORY, MAX_HISTORY),
+            ..self
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// Sending signals to GPU processes, from the process table context menu or
+// `rgm kill`.
+use crate::cli::CliOptions;
+use crate::data::ProcessInfo;
+use crate::recording::load_monitors;
+use std::io;
+use thiserror::Error;
+
+/// The signals RGM offers; enough to stop, pause or resume a job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Term,
+    Kill,
+    Stop,
+    Cont,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 4] = [Signal::Term, Signal::Kill, Signal::Stop, Signal::Cont];
+
+    /// Parse `TERM`, `SIGTERM` or `15`, ignoring case.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_uppercase();
+        let name = text.strip_prefix("SIG").unwrap_or(&text);
+        Self::ALL
+            .into_iter()
+            .find(|signal| signal.name()[3..] == *name || signal.number().to_string() == name)
+    }
+
+    /// Conventional name, e.g. `SIGTERM`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Term => "SIGTERM",
+            Signal::Kill => "SIGKILL",
+            Signal::Stop => "SIGSTOP",
+            Signal::Cont => "SIGCONT",
+        }
+    }
+
+    /// What the signal does to the process, for the confirmation dialog.
+    pub fn description(self) -> &'static str {
+        match self {
+            Signal::Term => "Ask the process to exit; it may clean up first",
+            Signal::Kill => "End the process immediately, without cleaning up",
+            Signal::Stop => "Pause the process until it is continued",
+            Signal::Cont => "Resume a paused process",
+        }
+    }
+
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Term => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Cont => libc::SIGCONT,
+        }
+    }
+}
+
+impl std::fmt::Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SignalError {
+    #[error("PID {0} is not a process RGM can signal")]
+    InvalidPid(u32),
+    #[error("permission denied sending {signal} to PID {pid}: it belongs to another user")]
+    PermissionDenied { pid: u32, signal: Signal },
+    #[error("PID {0} no longer exists")]
+    NoSuchProcess(u32),
+    #[error("cannot signal PID {pid}: {source}")]
+    Failed { pid: u32, source: io::Error },
+}
+
+/// Send `signal` to the process `pid`.
+pub fn send(pid: u32, signal: Signal) -> Result<(), SignalError> {
+    // 0 and negative values would signal whole process groups, or everything
+    let target = match libc::pid_t::try_from(pid) {
+        Ok(target) if target > 0 => target,
+        _ => return Err(SignalError::InvalidPid(pid)),
+    };
+    // SAFETY: kill(2) takes plain integers and has no memory effects
+    if unsafe { libc::kill(target, signal.number()) } == 0 {
+        return Ok(());
+    }
+    let error = io::Error::last_os_error();
+    Err(match error.raw_os_error() {
+        Some(libc::EPERM) => SignalError::PermissionDenied { pid, signal },
+        Some(libc::ESRCH) => SignalError::NoSuchProcess(pid),
+        _ => SignalError::Failed { pid, source: error },
+    })
+}
+
+/// `rgm kill`: send `options.signal` to each of `options.pids`, provided it
+/// is using a monitored GPU. Every PID is tried; the error lists failures.
+pub fn run(options: &CliOptions) -> Result<(), String> {
+    if options.replay.is_some() {
+        return Err("cannot signal the processes of a recording".into());
+    }
+    if options.pids.is_empty() {
+        return Err("no PID given".into());
+    }
+    let report = load_monitors(options);
+    if report.monitors.is_empty() {
+        let reasons: Vec<String> = report
+            .failures
+            .iter()
+            .map(|f| format!("  {}: {}", f.backend, f.error))
+            .collect();
+        return Err(format!("no compatible GPU found\n{}", reasons.join("\n")));
+    }
+    let processes: Vec<ProcessInfo> = report
+        .monitors
+        .iter()
+        .filter_map(|monitor| monitor.sample().ok())
+        .flat_map(|(_, processes)| processes)
+        .collect();
+
+    let mut failures = Vec::new();
+    for &pid in &options.pids {
+        let Some(process) = processes.iter().find(|p| p.pid == pid) else {
+            failures.push(format!("PID {pid} is not using a monitored GPU"));
+            continue;
+        };
+        match send(pid, options.signal) {
+            Ok(()) => println!("Sent {} to {} ({pid})", options.signal, process.name),
+            Err(e) => failures.push(e.to_string()),
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("\n"))
+    }
+}
@@ -0,0 +1,593 @@
+// Terminal UI: an nvtop-style view for SSH sessions.
+//
+// Sampling, recording, alerts and the history buffers are the GUI's own
+// (`Sampler`), so both front ends show the same data; this module only
+// draws it with ANSI escape codes and handles the keyboard.
+use crate::alerts::{configured_rules, AlertAction, AlertEngine};
+use crate::cli::{format_duration, CliOptions};
+use crate::config::ConfigWatcher;
+use crate::csv_log::CsvWriter;
+use crate::data::{GpuData, ProcessInfo};
+use crate::history::{History, SeriesPoint};
+use crate::processes::{visible_processes, ProcessSort, SortKey};
+use crate::recording::{load_supervised, Recorder};
+use crate::sampler::{DeviceState, DeviceStatus, Sampler};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Stylize;
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Time span of the sparklines when `--history` is not given.
+pub const DEFAULT_HISTORY: Duration = Duration::from_secs(5 * 60);
+
+const SPARK_LEVELS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// A horizontal bar `width` cells wide, filled to `fraction`.
+pub fn gauge(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// One cell per value, scaled so that `max` is a full block. Missing values
+/// are blank.
+pub fn sparkline(values: &[Option<f64>], max: f64) -> String {
+    let top = (SPARK_LEVELS.len() - 1) as f64;
+    values
+        .iter()
+        .map(|value| match value {
+            Some(v) if max > 0.0 => {
+                // Anything above zero shows at least the lowest bar
+                let level = (v / max * top).ceil().clamp(0.0, top) as usize;
+                SPARK_LEVELS[level.max(usize::from(*v > 0.0))]
+            }
+            _ => ' ',
+        })
+        .collect()
+}
+
+/// Spread history points over `width` columns covering the `span` seconds
+/// up to `latest`, keeping the highest value that lands in each column.
+pub fn columns(points: &[SeriesPoint], latest: f64, span: f64, width: usize) -> Vec<Option<f64>> {
+    let mut columns = vec![None; width];
+    if width == 0 || span <= 0.0 {
+        return columns;
+    }
+    let from = latest - span;
+    for point in points {
+        let position = (point.timestamp - from) / span * width as f64;
+        if position < 0.0 {
+            continue;
+        }
+        let column = (position as usize).min(width - 1);
+        let cell: &mut Option<f64> = &mut columns[column];
+        *cell = Some(cell.map_or(point.avg, |v| v.max(point.avg)));
+    }
+    columns
+}
+
+/// Truncate or pad `text` to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let len = fitted.chars().count();
+    fitted.extend(std::iter::repeat_n(' ', width - len));
+    fitted
+}
+
+fn optional<T>(value: Option<T>, format: impl Fn(T) -> String) -> String {
+    value.map_or_else(|| "-".to_string(), format)
+}
+
+// Restores the terminal when dropped, including on panic
+struct TerminalGuard {
+    /// Log output would scribble over the screen, so logging is off while
+    /// it is shown; errors appear in the status line instead
+    log_level: log::LevelFilter,
+}
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        let log_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        Ok(Self { log_level })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+        log::set_max_level(self.log_level);
+    }
+}
+
+// What the user has selected; everything else comes from the sampler
+struct View {
+    selected: usize,
+    sort: ProcessSort,
+    filter: String,
+    /// Keys go to the filter until Enter or Esc
+    editing_filter: bool,
+    scroll: usize,
+    window: Duration,
+}
+
+impl View {
+    /// Apply a key press. Returns false to quit.
+    fn handle(&mut self, key: KeyEvent, devices: usize) -> bool {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => {}
+            }
+            self.scroll = 0;
+            return true;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Right | KeyCode::Tab | KeyCode::Char('l') => {
+                self.selected = (self.selected + 1) % devices;
+                self.scroll = 0;
+            }
+            KeyCode::Left | KeyCode::BackTab | KeyCode::Char('h') => {
+                self.selected = (self.selected + devices - 1) % devices;
+                self.scroll = 0;
+            }
+            KeyCode::Char(digit @ '0'..='9') => {
+                let index = digit as usize - '0' as usize;
+                if index < devices {
+                    self.selected = index;
+                    self.scroll = 0;
+                }
+            }
+            KeyCode::Char('s') => self.sort.select(self.sort.key.next()),
+            KeyCode::Char('r') => self.sort.descending = !self.sort.descending,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown => self.scroll += 10,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            _ => {}
+        }
+        true
+    }
+}
+
+/// Run the terminal UI until the user quits.
+pub fn run(options: &CliOptions, mut config: ConfigWatcher) -> Result<(), String> {
+    let (report, probe, rescan) = load_supervised(options);
+    if report.monitors.is_empty() {
+        let reasons: Vec<String> = report
+            .failures
+            .iter()
+            .map(|f| format!("  {}: {}", f.backend, f.error))
+            .collect();
+        return Err(format!("no compatible GPU found\n{}", reasons.join("\n")));
+    }
+
+    let alerts = AlertEngine::new(configured_rules(
+        config.config(),
+        options.alerts.as_deref(),
+    )?);
+    let recorder = match &options.record {
+        Some(path) => {
+            let infos = report
+                .monitors
+                .iter()
+                .map(|m| m.get_static_info())
+                .collect();
+            Some(
+                Recorder::create(path, infos)
+                    .map_err(|e| format!("cannot record to {}: {e}", path.display()))?,
+            )
+        }
+        None => None,
+    };
+    let csv = match &options.csv {
+        Some(path) => Some(
+            CsvWriter::create(path, options.csv_options.clone())
+                .map_err(|e| format!("cannot write {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    let window = options.history.unwrap_or(DEFAULT_HISTORY);
+    let mut sampler = Sampler::start(
+        report.monitors,
+        options.interval(),
+        window,
+        recorder,
+        csv,
+        alerts,
+    );
+    sampler.supervise(probe, rescan);
+    let mut view = View {
+        selected: config
+            .config()
+            .ui
+            .device
+            .as_deref()
+            .and_then(|pattern| {
+                sampler
+                    .devices
+                    .iter()
+                    .position(|d| d.gpu_info.matches(pattern))
+            })
+            .unwrap_or(0),
+        sort: ProcessSort::default(),
+        filter: String::new(),
+        editing_filter: false,
+        scroll: 0,
+        window,
+    };
+
+    let _guard = TerminalGuard::enter().map_err(|e| e.to_string())?;
+    let mut out = io::stdout();
+    let mut redraw = true;
+    loop {
+        redraw |= sampler.update(view.window);
+        let reloaded = sampler
+            .alerts
+            .lock()
+            .unwrap()
+            .reload(&mut config, options.alerts.as_deref());
+        if let Some(sampling) = reloaded {
+            let sampling = options.sampling_overrides.reloaded(&sampling);
+            if let Some(interval) = sampling.interval {
+                sampler.set_interval(interval);
+            }
+            if let Some(history) = sampling.history {
+                view.window = history;
+                redraw = true;
+            }
+        }
+        if redraw {
+            let (width, height) = terminal::size().map_err(|e| e.to_string())?;
+            let lines = render(&view, &sampler, width as usize, height as usize);
+            draw(&mut out, &lines).map_err(|e| e.to_string())?;
+            redraw = false;
+        }
+
+        if !event::poll(Duration::from_millis(100)).map_err(|e| e.to_string())? {
+            continue;
+        }
+        match event::read().map_err(|e| e.to_string())? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if !view.handle(key, sampler.devices.len()) {
+                    return Ok(());
+                }
+                redraw = true;
+            }
+            Event::Resize(..) => redraw = true,
+            _ => {}
+        }
+    }
+}
+
+fn draw(out: &mut impl Write, lines: &[String]) -> io::Result<()> {
+    for (row, line) in lines.iter().enumerate() {
+        queue!(
+            out,
+            cursor::MoveTo(0, row as u16),
+            crossterm::style::Print(line),
+            terminal::Clear(ClearType::UntilNewLine)
+        )?;
+    }
+    queue!(out, terminal::Clear(ClearType::FromCursorDown))?;
+    out.flush()
+}
+
+/// Every line of the screen, already fitted to `width` and styled.
+fn render(view: &View, sampler: &Sampler, width: usize, height: usize) -> Vec<String> {
+    let mut lines = Vec::with_capacity(height);
+    let title = format!(
+        " RGM {} · {} GPU(s) · every {} · last {}",
+        env!("CARGO_PKG_VERSION"),
+        sampler.devices.len(),
+        format_duration(sampler.interval()),
+        format_duration(view.window),
+    );
+    lines.push(fit(&title, width).reverse().to_string());
+
+    // One gauge line per device
+    for (index, device) in sampler.devices.iter().enumerate() {
+        lines.push(overview_line(index, device, index == view.selected, width));
+    }
+    lines.push(String::new());
+
+    let device = &sampler.devices[view.selected.min(sampler.devices.len() - 1)];
+    let info = &device.gpu_info;
+    lines.push(
+        fit(
+            &format!(
+                "GPU {} · {} · {} · driver {}",
+                view.selected, info.name, info.bus_id, info.driver_version
+            ),
+            width,
+        )
+        .bold()
+        .to_string(),
+    );
+    if let DeviceStatus::Offline(error) = &device.status {
+        let offline = format!("⚠ Device offline: {error}. Reconnecting…");
+        lines.push(fit(&offline, width).red().bold().to_string());
+    } else if let Some(latest) = device.history.latest() {
+        lines.push(fit(&details(latest), width));
+    }
+
+    let firing: Vec<String> = sampler
+        .alerts
+        .lock()
+        .unwrap()
+        .firing()
+        .filter(|(rule, _)| rule.actions.contains(&AlertAction::Highlight))
+        .filter_map(|(rule, device_id)| {
+            let index = sampler.device_index(device_id)?;
+            Some(format!(
+                "⚠ GPU {index} · {}: {}",
+                rule.name,
+                rule.condition()
+            ))
+        })
+        .collect();
+    for alert in firing {
+        lines.push(fit(&alert, width).red().bold().to_string());
+    }
+
+    lines.push(String::new());
+    lines.extend(sparklines(device, view.window, width));
+    lines.push(String::new());
+    lines.extend(process_table(
+        view,
+        device,
+        width,
+        height.saturating_sub(lines.len() + 1),
+    ));
+
+    // Sampling errors, if any, sit just above the footer
+    let status = sampler.errors.last.as_ref().map(|last| {
+        let text = format!(" ⚠ {} · last: {last}", sampler.errors.summary());
+        fit(&text, width).yellow().to_string()
+    });
+    let reserved = 1 + usize::from(status.is_some());
+    lines.truncate(height.saturating_sub(reserved));
+    while lines.len() + reserved < height {
+        lines.push(String::new());
+    }
+    lines.extend(status);
+    let footer = if view.editing_filter {
+        format!(" Filter: {}█  Enter done  Esc clear", view.filter)
+    } else {
+        " q quit  ←/→ GPU  s sort  r reverse  / filter  ↑/↓ scroll".to_string()
+    };
+    lines.push(fit(&footer, width).reverse().to_string());
+    lines
+}
+
+fn overview_line(index: usize, device: &DeviceState, selected: bool, width: usize) -> String {
+    let marker = if selected { '>' } else { ' ' };
+    let name = fit(&device.gpu_info.name, 24);
+    if let DeviceStatus::Offline(error) = &device.status {
+        let line = fit(
+            &format!("{marker}{index:>2} {name} offline: {error}"),
+            width,
+        );
+        return line.red().to_string();
+    }
+    let latest = device.history.latest();
+    // Fixed part: marker, index, name, labels, values; the rest is bars
+    let bar = width.saturating_sub(78).clamp(4, 30);
+    let utilization = latest.and_then(|d| d.utilization);
+    let memory = latest.and_then(|d| d.memory_used.zip(d.memory_total));
+    let line = format!(
+        "{marker}{index:>2} {name} GPU [{}] {:>4}  MEM [{}] {:>13}  {:>5}  {:>6}",
+        gauge(utilization.map_or(0.0, |u| u as f64 / 100.0), bar),
+        optional(utilization, |u| format!("{u:.0}%")),
+        gauge(
+            memory.map_or(0.0, |(used, total)| used / total.max(f64::EPSILON)),
+            bar
+        ),
+        optional(memory, |(used, total)| format!("{used:.1}/{total:.1}G")),
+        optional(latest.and_then(|d| d.temperature), |t| format!("{t}°C")),
+        optional(latest.and_then(|d| d.power_usage), |p| format!("{p:.0}W")),
+    );
+    let line = fit(&line, width);
+    if selected {
+        line.bold().to_string()
+    } else {
+        line
+    }
+}
+
+fn details(latest: &GpuData) -> String {
+    format!(
+        "Temp {}  Power {}  Fan {}  GPU clock {}  Mem clock {}  PCIe TX {} RX {}  Sampled in {}",
+        optional(latest.temperature, |t| format!("{t}°C")),
+        optional(latest.power_usage, |usage| match latest.power_limit {
+            Some(limit) => format!("{usage:.0}/{limit:.0} W"),
+            None => format!("{usage:.0} W"),
+        }),
+        optional(latest.fan_speed, |f| format!("{f}%")),
+        optional(latest.gpu_clock, |c| format!("{c} MHz")),
+        optional(latest.memory_clock, |c| format!("{c} MHz")),
+        optional(latest.pcie_throughput_tx, |tx| format!("{tx:.0} MB/s")),
+        optional(latest.pcie_throughput_rx, |rx| format!("{rx:.0} MB/s")),
+        optional(latest.collection_time, |ms| format!("{ms:.1} ms")),
+    )
+}
+
+type Mapper = fn(&GpuData) -> Option<f64>;
+
+// A sparkline: label, value mapper, fixed top of scale if any, and how to
+// print the current value
+struct Spark {
+    label: &'static str,
+    value: Mapper,
+    max: Option<Mapper>,
+    current: fn(&GpuData) -> Option<String>,
+}
+
+const SPARKS: [Spark; 4] = [
+    Spark {
+        label: "Utilization",
+        value: |d| d.utilization.map(f64::from),
+        max: Some(|_| Some(100.0)),
+        current: |d| d.utilization.map(|u| format!("{u:.0}%")),
+    },
+    Spark {
+        label: "Memory",
+        value: |d| d.memory_used,
+        max: Some(|d| d.memory_total),
+        current: |d| d.memory_used.map(|m| format!("{m:.1} GiB")),
+    },
+    Spark {
+        label: "Temperature",
+        value: |d| d.temperature.map(f64::from),
+        max: None,
+        current: |d| d.temperature.map(|t| format!("{t}°C")),
+    },
+    Spark {
+        label: "Power",
+        value: |d| d.power_usage,
+        max: Some(|d| d.power_limit),
+        current: |d| d.power_usage.map(|p| format!("{p:.0} W")),
+    },
+];
+
+fn sparklines(device: &DeviceState, window: Duration, width: usize) -> Vec<String> {
+    const LABEL: usize = 12;
+    const VALUE: usize = 10;
+    let cells = width.saturating_sub(LABEL + VALUE + 2);
+    let history: &History = &device.history;
+    let Some(latest) = history.latest() else {
+        return vec![fit("Waiting for the first sample…", width)];
+    };
+    let span = window.as_secs_f64();
+
+    SPARKS
+        .iter()
+        .filter(|spark| (spark.value)(latest).is_some())
+        .map(|spark| {
+            let (_, points) = history.series(span, cells, spark.value);
+            let values = columns(&points, latest.timestamp, span, cells);
+            let peak = values.iter().flatten().fold(0.0_f64, |a, b| a.max(*b));
+            // Fixed scales where the metric has one, else the peak seen
+            let max = spark
+                .max
+                .and_then(|max| max(latest))
+                .unwrap_or(peak)
+                .max(peak);
+            format!(
+                "{} {} {}",
+                fit(spark.label, LABEL),
+                sparkline(&values, max).green(),
+                fit(&(spark.current)(latest).unwrap_or_default(), VALUE)
+            )
+        })
+        .collect()
+}
+
+// Process table columns: heading, width, sort key. The last one takes the
+// remaining width.
+const PROCESS_COLUMNS: [(&str, usize, SortKey); 12] = [
+    ("PID", 8, SortKey::Pid),
+    ("USER", 10, SortKey::User),
+    ("TYPE", 5, SortKey::Type),
+    ("NAME", 16, SortKey::Name),
+    ("MEM MiB", 9, SortKey::GpuMemory),
+    ("GPU%", 6, SortKey::Gpu),
+    ("SM%", 5, SortKey::Sm),
+    ("ENC%", 5, SortKey::Encoder),
+    ("DEC%", 5, SortKey::Decoder),
+    ("CPU%", 6, SortKey::Cpu),
+    ("RSS MiB", 9, SortKey::HostMemory),
+    ("COMMAND", 0, SortKey::Command),
+];
+
+fn process_cells(process: &ProcessInfo) -> [String; 12] {
+    let percent = |value: Option<f32>| optional(value, |v| format!("{v:.0}"));
+    let mib = |bytes: u64| format!("{:.1}", bytes as f64 / 1024.0 / 1024.0);
+    [
+        process.pid.to_string(),
+        process.user.clone(),
+        optional(process.process_type, |t| t.label().to_string()),
+        process.name.clone(),
+        mib(process.memory_usage),
+        format!("{:.1}", process.gpu_utilization),
+        percent(process.sm_utilization),
+        percent(process.encoder_utilization),
+        percent(process.decoder_utilization),
+        format!("{:.1}", process.cpu_percent),
+        mib(process.host_memory),
+        if process.command_line.is_empty() {
+            process.name.clone()
+        } else {
+            process.command_line.clone()
+        },
+    ]
+}
+
+/// Cells separated by spaces; numbers right-aligned, text left-aligned.
+fn process_row(cells: &[String], width: usize) -> String {
+    let mut row = String::new();
+    for (index, (cell, (_, column_width, key))) in cells.iter().zip(PROCESS_COLUMNS).enumerate() {
+        if index > 0 {
+            row.push(' ');
+        }
+        if column_width == 0 {
+            row.push_str(cell);
+        } else if key.descending_first() || key == SortKey::Pid {
+            row.push_str(&format!("{cell:>column_width$.column_width$}"));
+        } else {
+            row.push_str(&fit(cell, column_width));
+        }
+    }
+    fit(&row, width)
+}
+
+fn process_table(view: &View, device: &DeviceState, width: usize, rows: usize) -> Vec<String> {
+    if rows < 2 {
+        return Vec::new();
+    }
+    let arrow = if view.sort.descending { '▼' } else { '▲' };
+    let headings: Vec<String> = PROCESS_COLUMNS
+        .iter()
+        .map(|(text, _, key)| {
+            if view.sort.key == *key {
+                format!("{text}{arrow}")
+            } else {
+                text.to_string()
+            }
+        })
+        .collect();
+    let mut lines = vec![process_row(&headings, width).reverse().to_string()];
+
+    let processes = visible_processes(&device.processes, &view.filter, view.sort);
+    let scroll = view.scroll.min(processes.len().saturating_sub(1));
+    for process in processes.iter().skip(scroll).take(rows - 1) {
+        lines.push(process_row(&process_cells(process), width));
+    }
+    if processes.is_empty() {
+        let message = if device.processes.is_empty() {
+            "No GPU processes".to_string()
+        } else {
+            format!("No processes match '{}'", view.filter)
+        };
+        lines.push(fit(&message, width).dim().to_string());
+    }
+    lines
+}
@@ -0,0 +1,204 @@
+// Alert rule parsing, sustained duration, hysteresis and actions.
+use rgm_ui::alerts::{
+    format_event, run_command, AlertAction, AlertEngine, AlertMetric, AlertRule, AlertState,
+    Comparator, DerivedMetric,
+};
+use rgm_ui::data::{GpuData, GpuInfo, Metric};
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rgm-test-{}-{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn device() -> GpuInfo {
+    GpuInfo {
+        name: "Test GPU".into(),
+        bus_id: "0000:01:00.0".into(),
+        ..Default::default()
+    }
+}
+
+fn sample(timestamp: f64, temperature: Option<u32>) -> GpuData {
+    GpuData {
+        temperature,
+        ..GpuData::empty("0000:01:00.0", timestamp)
+    }
+}
+
+fn overheat(sustained: Duration, hysteresis: f64) -> AlertRule {
+    AlertRule {
+        name: "overheat".into(),
+        metric: AlertMetric::Metric(Metric::Temperature),
+        comparator: Comparator::Above,
+        threshold: 85.0,
+        sustained,
+        hysteresis,
+        actions: vec![AlertAction::Highlight],
+    }
+}
+
+/// Feed `(timestamp, temperature)` samples and collect the event states.
+fn run(engine: &mut AlertEngine, samples: &[(f64, Option<u32>)]) -> Vec<(f64, AlertState)> {
+    samples
+        .iter()
+        .flat_map(|&(t, temp)| {
+            engine
+                .evaluate(&device(), &sample(t, temp))
+                .into_iter()
+                .map(move |e| (t, e.state))
+        })
+        .collect()
+}
+
+#[test]
+fn rules_parse_from_json_with_defaults_and_aliases() {
+    let rules: Vec<AlertRule> = serde_json::from_str(
+        r#"[
+            { "name": "overheat", "metric": "temperature", "comparator": ">",
+              "threshold": 85, "sustained": "30s", "hysteresis": 5,
+              "actions": [{ "type": "notify" }, { "type": "log", "path": "alerts.log" }] },
+            { "name": "vram", "metric": "memory_percent", "comparator": "above", "threshold": 95 }
+        ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(rules[0].metric, AlertMetric::Metric(Metric::Temperature));
+    assert_eq!(rules[0].comparator, Comparator::Above);
+    assert_eq!(rules[0].sustained, Duration::from_secs(30));
+    assert_eq!(
+        rules[0].actions[1],
+        AlertAction::Log {
+            path: "alerts.log".into()
+        }
+    );
+    assert_eq!(
+        rules[1].metric,
+        AlertMetric::Derived(DerivedMetric::MemoryPercent)
+    );
+    assert_eq!(rules[1].sustained, Duration::ZERO);
+    assert_eq!(rules[1].actions, vec![AlertAction::Highlight]);
+
+    let unknown = serde_json::from_str::<Vec<AlertRule>>(
+        r#"[{ "name": "x", "metric": "voltage", "comparator": ">", "threshold": 1 }]"#,
+    );
+    assert!(unknown.is_err());
+}
+
+#[test]
+fn fires_only_after_the_sustained_duration() {
+    let mut engine = AlertEngine::new(vec![overheat(Duration::from_secs(3), 0.0)]);
+    let events = run(
+        &mut engine,
+        &[
+            (0.0, Some(90)),
+            (1.0, Some(80)), // dip restarts the clock
+            (2.0, Some(90)),
+            (4.0, Some(91)),
+            (5.0, Some(92)),
+            (6.0, Some(92)),
+        ],
+    );
+    assert_eq!(events, vec![(5.0, AlertState::Firing)]);
+    assert_eq!(engine.firing().count(), 1);
+}
+
+#[test]
+fn resolves_only_past_the_hysteresis_margin() {
+    let mut engine = AlertEngine::new(vec![overheat(Duration::ZERO, 5.0)]);
+    let events = run(
+        &mut engine,
+        &[
+            (0.0, Some(86)),
+            (1.0, Some(84)),
+            (2.0, Some(81)),
+            (3.0, Some(80)),
+            (4.0, Some(86)),
+        ],
+    );
+    assert_eq!(
+        events,
+        vec![
+            (0.0, AlertState::Firing),
+            (3.0, AlertState::Resolved),
+            (4.0, AlertState::Firing)
+        ]
+    );
+}
+
+#[test]
+fn unavailable_values_neither_fire_nor_resolve() {
+    let mut engine = AlertEngine::new(vec![overheat(Duration::from_secs(2), 0.0)]);
+    let events = run(
+        &mut engine,
+        &[(0.0, Some(90)), (1.0, None), (2.0, Some(90))],
+    );
+    assert!(
+        events.is_empty(),
+        "missing value must reset the pending rule"
+    );
+
+    let events = run(&mut engine, &[(4.0, Some(90)), (5.0, None), (6.0, None)]);
+    assert_eq!(events, vec![(4.0, AlertState::Firing)]);
+    assert_eq!(engine.firing().count(), 1);
+}
+
+#[test]
+fn devices_are_tracked_separately() {
+    let mut engine = AlertEngine::new(vec![overheat(Duration::ZERO, 0.0)]);
+    let other = GpuData {
+        temperature: Some(95),
+        ..GpuData::empty("0000:02:00.0", 0.0)
+    };
+    assert_eq!(engine.evaluate(&device(), &other).len(), 1);
+    assert!(engine
+        .evaluate(&device(), &sample(0.0, Some(60)))
+        .is_empty());
+
+    let firing: Vec<_> = engine.firing().map(|(_, id)| id.to_string()).collect();
+    assert_eq!(firing, vec!["0000:02:00.0"]);
+}
+
+#[test]
+fn derived_percentages_need_both_metrics() {
+    let data = GpuData {
+        memory_used: Some(7.6),
+        memory_total: Some(8.0),
+        power_usage: Some(150.0),
+        ..GpuData::empty("0000:01:00.0", 0.0)
+    };
+    let memory = AlertMetric::Derived(DerivedMetric::MemoryPercent);
+    let power = AlertMetric::Derived(DerivedMetric::PowerPercent);
+    assert!((memory.value(&data).unwrap() - 95.0).abs() < 1e-9);
+    assert_eq!(power.value(&data), None);
+    assert_eq!(memory.key(), "memory_percent");
+    assert_eq!(AlertMetric::Metric(Metric::GpuClock).key(), "gpu_clock");
+}
+
+#[test]
+fn commands_receive_the_event_in_env_and_stdin() {
+    let dir = temp_dir("alert-command");
+    let out = dir.join("out.txt");
+    let mut engine = AlertEngine::new(vec![overheat(Duration::ZERO, 0.0)]);
+    let event = engine
+        .evaluate(&device(), &sample(0.0, Some(90)))
+        .pop()
+        .unwrap();
+
+    let command = format!(
+        "echo \"$RGM_ALERT_RULE $RGM_ALERT_STATE $RGM_ALERT_METRIC $RGM_ALERT_VALUE $RGM_DEVICE_ID\" > {0}; cat >> {0}",
+        out.display()
+    );
+    assert!(run_command(&command, &event).unwrap().success());
+
+    let text = std::fs::read_to_string(&out).unwrap();
+    let (env, json) = text.split_once('\n').unwrap();
+    assert_eq!(env, "overheat firing temperature 90 0000:01:00.0");
+    let json: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(json["state"], "firing");
+    assert_eq!(json["device"]["name"], "Test GPU");
+    assert_eq!(json["sample"]["temperature"], 90);
+    assert!(format_event(&event).contains("FIRING overheat 0000:01:00.0 temperature = 90.00"));
+}
@@ -0,0 +1,131 @@
+// Drive the AMD backend from fake sysfs trees under tests/fixtures/sysfs.
+use rgm_ui::data::GpuVendor;
+use rgm_ui::fdinfo::DrmClients;
+use rgm_ui::monitor::{AmdgpuMonitor, GpuMonitor, MonitorError};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/sysfs")
+        .join(name)
+}
+
+/// Processes come from the fixture procfs, never the host's.
+fn procfs() -> Arc<DrmClients> {
+    DrmClients::new(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/proc"))
+}
+
+fn single_monitor(name: &str) -> AmdgpuMonitor {
+    let mut monitors =
+        AmdgpuMonitor::enumerate_in(&fixture(name), &procfs()).expect("fixture has a card");
+    assert_eq!(monitors.len(), 1, "expected exactly one amdgpu card");
+    monitors.remove(0)
+}
+
+#[test]
+fn dgpu_reports_every_metric() {
+    let monitor = single_monitor("dgpu");
+
+    let info = monitor.get_static_info();
+    assert_eq!(info.vendor, GpuVendor::Amd);
+    assert_eq!(info.bus_id, "0000:03:00.0");
+    assert_eq!(info.vbios_version, "113-D4120100-100");
+    assert_eq!(info.pcie_gen, 4);
+    assert_eq!(info.pcie_width, 16);
+
+    let (data, _) = monitor.sample().unwrap();
+    assert_eq!(data.device_id, "0000:03:00.0");
+    assert_eq!(data.utilization, Some(87.0));
+    assert_eq!(data.memory_used, Some(4.0));
+    assert_eq!(data.memory_total, Some(16.0));
+    assert_eq!(data.temperature, Some(65));
+    assert_eq!(data.fan_speed, Some(50));
+    assert_eq!(data.gpu_clock, Some(2100));
+    assert_eq!(data.memory_clock, Some(1000));
+    assert_eq!(data.power_usage, Some(180.0));
+    assert_eq!(data.power_limit, Some(250.0));
+    // amdgpu has no PCIe throughput counters
+    assert_eq!(data.pcie_throughput_tx, None);
+    assert!(data.collection_time.is_some_and(|ms| ms >= 0.0));
+    assert!(data.errors.is_empty(), "{:?}", data.errors);
+}
+
+#[test]
+fn igpu_with_missing_nodes_reports_them_unavailable() {
+    // card0 is bound to i915 and must be skipped
+    let monitor = single_monitor("igpu");
+    assert_eq!(monitor.get_static_info().bus_id, "0000:05:00.0");
+
+    let (data, _) = monitor.sample().unwrap();
+    assert_eq!(data.utilization, Some(12.0));
+    assert_eq!(data.temperature, Some(48));
+    // power1_average is absent, power1_input is used instead
+    assert_eq!(data.power_usage, Some(9.0));
+    assert_eq!(data.power_limit, None);
+    assert_eq!(data.memory_used, None);
+    assert_eq!(data.memory_total, None);
+    assert_eq!(data.fan_speed, None);
+    assert_eq!(data.memory_clock, None);
+    // Missing attributes are unsupported, not read errors
+    assert!(data.errors.is_empty(), "{:?}", data.errors);
+}
+
+#[test]
+fn sensors_split_across_hwmon_directories_are_combined() {
+    let monitor = single_monitor("multi_hwmon");
+
+    let (data, _) = monitor.sample().unwrap();
+    assert_eq!(data.temperature, Some(55));
+    assert_eq!(data.fan_speed, Some(100));
+    assert_eq!(data.gpu_clock, Some(2500));
+    assert_eq!(data.memory_clock, Some(1250));
+    assert_eq!(data.power_usage, Some(300.0));
+    assert_eq!(data.power_limit, Some(355.0));
+    assert_eq!(data.memory_total, Some(24.0));
+}
+
+#[test]
+fn missing_drm_directory_reports_no_device() {
+    assert!(matches!(
+        AmdgpuMonitor::enumerate_in(&fixture("does-not-exist"), &procfs()),
+        Err(MonitorError::NoDevice("amdgpu"))
+    ));
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            std::fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+#[test]
+fn opening_a_vanished_card_reports_device_lost() {
+    let root = std::env::temp_dir().join(format!("rgm-test-{}-vanished", std::process::id()));
+    let error = AmdgpuMonitor::from_path(root.join("card0/device"), &procfs())
+        .err()
+        .unwrap();
+    assert!(matches!(error, MonitorError::DeviceLost));
+}
+
+#[test]
+fn unplugged_card_reports_device_lost() {
+    let root = std::env::temp_dir().join(format!("rgm-test-{}-unplug", std::process::id()));
+    copy_dir(&fixture("dgpu"), &root);
+    let monitor = AmdgpuMonitor::enumerate_in(&root, &procfs())
+        .unwrap()
+        .remove(0);
+    assert!(monitor.sample().is_ok());
+
+    std::fs::remove_dir_all(&root).unwrap();
+    let error = monitor.sample().unwrap_err();
+    assert!(matches!(error, MonitorError::DeviceLost));
+    assert_eq!(error.kind(), "device lost");
+}
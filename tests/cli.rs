@@ -0,0 +1,168 @@
+// Command-line parsing.
+use rgm_ui::cli::{
+    format_duration, parse_duration, CliError, CliOptions, Mode, OutputFormat, SamplingOverrides,
+    DEFAULT_INTERVAL,
+};
+use rgm_ui::config::SamplingConfig;
+use rgm_ui::signals::Signal;
+use std::time::Duration;
+
+#[test]
+fn no_arguments_opens_the_gui() {
+    let options = CliOptions::parse(Vec::<String>::new()).unwrap();
+    assert_eq!(options, CliOptions::default());
+    assert_eq!(options.mode, Mode::Gui);
+    assert_eq!(options.interval(), DEFAULT_INTERVAL);
+}
+
+#[test]
+fn headless_with_interval_and_count() {
+    let options = CliOptions::parse(["--headless", "--interval", "500ms", "-c", "10"]).unwrap();
+    assert_eq!(options.mode, Mode::Headless);
+    assert_eq!(options.interval, Some(Duration::from_millis(500)));
+    assert_eq!(options.count, Some(10));
+}
+
+#[test]
+fn tui_mode_is_selectable() {
+    let options = CliOptions::parse(["--tui", "--history", "10m"]).unwrap();
+    assert_eq!(options.mode, Mode::Tui);
+    assert_eq!(options.history, Some(Duration::from_secs(600)));
+}
+
+#[test]
+fn kill_subcommand_takes_pids_and_a_signal() {
+    let options = CliOptions::parse(["kill", "-s", "KILL", "4242", "77"]).unwrap();
+    assert_eq!(options.mode, Mode::Kill);
+    assert_eq!(options.signal, Signal::Kill);
+    assert_eq!(options.pids, vec![4242, 77]);
+
+    let options = CliOptions::parse(["kill", "1200"]).unwrap();
+    assert_eq!(options.signal, Signal::Term);
+    assert!(CliOptions::parse(["kill", "self"]).is_err());
+    assert!(CliOptions::parse(["kill", "--signal", "HUP", "1"]).is_err());
+    // PIDs are only accepted after `kill`
+    assert!(CliOptions::parse(["1200"]).is_err());
+}
+
+#[test]
+fn log_level_is_configurable() {
+    let options = CliOptions::parse(["--log-level", "DEBUG"]).unwrap();
+    assert_eq!(options.log_level, log::LevelFilter::Debug);
+    assert_eq!(CliOptions::default().log_level, log::LevelFilter::Info);
+    assert!(CliOptions::parse(["--log-level", "loud"]).is_err());
+}
+
+#[test]
+fn inline_values_are_accepted() {
+    let options = CliOptions::parse(["--headless", "--interval=2m", "--count=3"]).unwrap();
+    assert_eq!(options.interval, Some(Duration::from_secs(120)));
+    assert_eq!(options.count, Some(3));
+}
+
+#[test]
+fn ndjson_format_is_selectable() {
+    let options = CliOptions::parse(["--headless", "--format", "ndjson"]).unwrap();
+    assert_eq!(options.format, OutputFormat::Ndjson);
+    assert!(CliOptions::parse(["--format", "xml"]).is_err());
+}
+
+#[test]
+fn invalid_arguments_are_reported() {
+    assert_eq!(
+        CliOptions::parse(["--bogus"]),
+        Err(CliError::UnknownOption("--bogus".into()))
+    );
+    assert_eq!(
+        CliOptions::parse(["--interval"]),
+        Err(CliError::MissingValue("--interval".into()))
+    );
+    assert!(matches!(
+        CliOptions::parse(["--count", "0"]),
+        Err(CliError::InvalidValue { .. })
+    ));
+}
+
+#[test]
+fn backends_and_devices_are_comma_lists() {
+    let options =
+        CliOptions::parse(["--backend", "amdgpu, Intel", "--device=0000:03:00.0"]).unwrap();
+    assert_eq!(options.backends, vec!["amdgpu", "Intel"]);
+    assert_eq!(options.devices, vec!["0000:03:00.0"]);
+    assert!(matches!(
+        CliOptions::parse(["--backend", "radeon"]),
+        Err(CliError::InvalidValue { .. })
+    ));
+}
+
+#[test]
+fn command_line_overrides_the_base_options() {
+    let base = CliOptions {
+        interval: Some(Duration::from_secs(5)),
+        listen: "0.0.0.0:9000".into(),
+        ..Default::default()
+    };
+    let options = CliOptions::parse_onto(base, ["--interval", "2s"]).unwrap();
+    assert_eq!(options.interval, Some(Duration::from_secs(2)));
+    assert_eq!(options.listen, "0.0.0.0:9000");
+    assert_eq!(
+        options.sampling_overrides,
+        SamplingOverrides {
+            interval: Some(Duration::from_secs(2)),
+            history: None,
+        }
+    );
+}
+
+#[test]
+fn command_line_sampling_wins_over_reloaded_config() {
+    let reloaded = SamplingConfig {
+        interval: Some(Duration::from_millis(500)),
+        history: Some(Duration::from_secs(30 * 3600)),
+    };
+    let options = CliOptions::parse(["--interval", "2s"]).unwrap();
+    assert_eq!(
+        options.sampling_overrides.reloaded(&reloaded),
+        SamplingConfig {
+            interval: None,
+            history: Some(Duration::from_secs(24 * 3600)),
+        }
+    );
+}
+
+#[test]
+fn durations_accept_common_units() {
+    assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+    assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+    assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+    assert!(parse_duration("0s").is_err());
+    assert!(parse_duration("5d").is_err());
+    // Too long for a Duration: an error, not a panic
+    assert!(parse_duration("99999999999999999999h").is_err());
+    assert!(matches!(
+        CliOptions::parse(["--interval", "99999999999999999999h"]),
+        Err(CliError::InvalidValue { .. })
+    ));
+}
+
+#[test]
+fn history_is_parsed_as_a_duration() {
+    let options = CliOptions::parse(["--history", "2h"]).unwrap();
+    assert_eq!(options.history, Some(Duration::from_secs(7200)));
+    assert!(CliOptions::parse(["--history", "forever"]).is_err());
+}
+
+#[test]
+fn durations_format_in_parseable_units() {
+    for (duration, text) in [
+        (Duration::from_millis(250), "250ms"),
+        (Duration::from_millis(1500), "1.5s"),
+        (Duration::from_secs(10), "10s"),
+        (Duration::from_secs(600), "10m"),
+        (Duration::from_secs(5400), "1.5h"),
+    ] {
+        assert_eq!(format_duration(duration), text);
+        assert_eq!(parse_duration(text), Ok(duration));
+    }
+}
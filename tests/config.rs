@@ -0,0 +1,214 @@
+// Config file parsing, validation, command-line precedence and reload.
+use rgm_ui::alerts::{AlertEngine, Comparator};
+use rgm_ui::cli::CliOptions;
+use rgm_ui::config::{Config, ConfigError, ConfigWatcher, Theme};
+use rgm_ui::csv_log::CsvColumn;
+use rgm_ui::data::GpuInfo;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rgm-test-{}-{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const TOML: &str = r#"
+[backends]
+enabled = ["amdgpu"]
+devices = ["0000:03:00.0"]
+
+[sampling]
+interval = "500ms"
+history = "30m"
+
+[exporter]
+listen = "0.0.0.0:9835"
+
+[csv]
+path = "gpu.csv"
+columns = "timestamp,temperature_c"
+rotate_size = "10M"
+
+[[alerts]]
+name = "overheat"
+metric = "temperature"
+comparator = ">"
+threshold = 85
+sustained = "30s"
+
+[ui]
+window_size = [1280, 800]
+theme = "light"
+
+[log]
+level = "warn"
+"#;
+
+fn invalid_field(text: &str, path: &str) -> String {
+    match Config::parse(text, Path::new(path)) {
+        Err(ConfigError::Invalid { field, .. }) => field,
+        other => panic!("expected a validation error, got {other:?}"),
+    }
+}
+
+#[test]
+fn toml_config_sets_every_section() {
+    let config = Config::parse(TOML, Path::new("config.toml")).unwrap();
+    assert_eq!(config.backends.enabled, vec!["amdgpu"]);
+    assert_eq!(config.sampling.interval, Some(Duration::from_millis(500)));
+    assert_eq!(config.alerts[0].comparator, Comparator::Above);
+    assert_eq!(config.ui.window_size, [1280.0, 800.0]);
+    assert_eq!(config.ui.theme, Theme::Light);
+    assert_eq!(config.ui.plot_height, 120.0);
+}
+
+#[test]
+fn json_config_is_read_by_extension() {
+    let config = Config::parse(
+        r#"{ "sampling": { "interval": "2s" }, "ui": { "theme": "dark" } }"#,
+        Path::new("config.json"),
+    )
+    .unwrap();
+    assert_eq!(config.sampling.interval, Some(Duration::from_secs(2)));
+    assert_eq!(config.ui.theme, Theme::Dark);
+}
+
+#[test]
+fn empty_file_is_the_default_config() {
+    let config = Config::parse("", Path::new("config.toml")).unwrap();
+    assert_eq!(config, Config::default());
+}
+
+#[test]
+fn typos_and_bad_values_name_the_file_and_field() {
+    let error = Config::parse("[sampling]\nintervall = \"1s\"\n", Path::new("rgm.toml"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.starts_with("rgm.toml: "), "{error}");
+    assert!(error.contains("intervall"), "{error}");
+
+    let error = Config::parse("[sampling]\ninterval = \"fast\"\n", Path::new("rgm.toml"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("line 2"), "{error}");
+
+    assert_eq!(
+        invalid_field("[backends]\nenabled = [\"cuda\"]\n", "c.toml"),
+        "backends.enabled[0]"
+    );
+    assert_eq!(
+        invalid_field("[csv]\ncolumns = \"timestamp,bogus\"\n", "c.toml"),
+        "csv.columns"
+    );
+    assert_eq!(
+        invalid_field("[ui]\nplot_height = 5\n", "c.toml"),
+        "ui.plot_height"
+    );
+    assert_eq!(
+        invalid_field("[sampling]\ninterval = \"10ms\"\n", "c.toml"),
+        "sampling.interval"
+    );
+    assert_eq!(
+        invalid_field("[sampling]\nhistory = \"48h\"\n", "c.toml"),
+        "sampling.history"
+    );
+    let duplicate = r#"{ "alerts": [
+        { "name": "hot", "metric": "temperature", "comparator": ">", "threshold": 80 },
+        { "name": "hot", "metric": "temperature", "comparator": ">", "threshold": 90 }
+    ] }"#;
+    assert_eq!(invalid_field(duplicate, "c.json"), "alerts[1].name");
+}
+
+#[test]
+fn command_line_takes_precedence_over_the_file() {
+    let config = Config::parse(TOML, Path::new("config.toml")).unwrap();
+    let defaults = config.cli_defaults();
+    assert_eq!(defaults.listen, "0.0.0.0:9835");
+    assert_eq!(defaults.log_level, log::LevelFilter::Warn);
+    assert_eq!(defaults.csv_options.rotate_size, Some(10 << 20));
+    assert_eq!(
+        defaults.csv_options.columns,
+        vec![CsvColumn::Timestamp, CsvColumn::Temperature]
+    );
+
+    let options =
+        CliOptions::parse_onto(defaults, ["--interval", "1s", "--backend", "intel"]).unwrap();
+    assert_eq!(options.interval, Some(Duration::from_secs(1)));
+    assert_eq!(options.history, Some(Duration::from_secs(1800)));
+    assert_eq!(options.backends, vec!["intel"]);
+    assert_eq!(options.devices, vec!["0000:03:00.0"]);
+}
+
+#[test]
+fn watcher_reloads_edits_and_keeps_the_last_good_config() {
+    let path = temp_dir("config-reload").join("config.toml");
+    std::fs::write(&path, "[sampling]\ninterval = \"1s\"\n").unwrap();
+    let mut watcher = ConfigWatcher::load(Some(path.clone())).unwrap();
+    assert_eq!(
+        watcher.config().sampling.interval,
+        Some(Duration::from_secs(1))
+    );
+    assert!(watcher.poll().is_none());
+
+    // Set the modification time explicitly; rewrites within the timestamp
+    // granularity would otherwise go unnoticed
+    let touch = |text: &str, secs: u64| {
+        std::fs::write(&path, text).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+            .unwrap();
+    };
+
+    touch("[sampling]\ninterval = \"2s\"\n", 1_000);
+    let reloaded = watcher.poll().unwrap().unwrap();
+    assert_eq!(reloaded.sampling.interval, Some(Duration::from_secs(2)));
+
+    touch("[sampling\n", 2_000);
+    assert!(watcher.poll().unwrap().is_err());
+    assert_eq!(
+        watcher.config().sampling.interval,
+        Some(Duration::from_secs(2))
+    );
+}
+
+#[test]
+fn reload_applies_rules_and_returns_the_sampling_settings() {
+    let path = temp_dir("config-engine-reload").join("config.toml");
+    std::fs::write(&path, "").unwrap();
+    let mut watcher = ConfigWatcher::load(Some(path.clone())).unwrap();
+    let mut engine = AlertEngine::new(Vec::new());
+    assert!(engine.reload(&mut watcher, None).is_none());
+
+    std::fs::write(&path, TOML).unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000))
+        .unwrap();
+    let sampling = engine.reload(&mut watcher, None).unwrap();
+    assert_eq!(sampling.interval, Some(Duration::from_millis(500)));
+    assert_eq!(sampling.history, Some(Duration::from_secs(30 * 60)));
+    assert_eq!(engine.rules().len(), 1);
+}
+
+#[test]
+fn missing_explicit_config_is_an_error() {
+    let path = temp_dir("config-missing").join("absent.toml");
+    assert!(matches!(
+        ConfigWatcher::load(Some(path)),
+        Err(ConfigError::Read { .. })
+    ));
+}
+
+#[test]
+fn devices_match_by_bus_id_uuid_or_name() {
+    let info = GpuInfo {
+        name: "NVIDIA GeForce RTX 4090".into(),
+        uuid: "GPU-1234".into(),
+        bus_id: "0000:01:00.0".into(),
+        ..Default::default()
+    };
+    assert!(info.matches("0000:01:00.0"));
+    assert!(info.matches("gpu-1234"));
+    assert!(info.matches("rtx 4090"));
+    assert!(!info.matches("0000:02:00.0"));
+}
@@ -0,0 +1,123 @@
+// CSV column selection, escaping and rotation.
+use rgm_ui::cli::parse_size;
+use rgm_ui::csv_log::{format_rfc3339, CsvColumn, CsvOptions, CsvWriter};
+use rgm_ui::data::{GpuData, GpuInfo};
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rgm-test-{}-{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn sample() -> (GpuInfo, GpuData) {
+    let info = GpuInfo {
+        name: "Radeon, \"Pro\"".into(),
+        bus_id: "0000:03:00.0".into(),
+        ..Default::default()
+    };
+    let data = GpuData {
+        device_id: "0000:03:00.0".into(),
+        timestamp: 0.0,
+        utilization: Some(55.0),
+        memory_used: Some(1.5),
+        memory_total: Some(8.0),
+        temperature: Some(61),
+        gpu_clock: Some(1900),
+        memory_clock: Some(1000),
+        power_usage: Some(120.0),
+        power_limit: Some(200.0),
+        fan_speed: None,
+        pcie_throughput_tx: None,
+        pcie_throughput_rx: None,
+        collection_time: None,
+        errors: Vec::new(),
+    };
+    (info, data)
+}
+
+#[test]
+fn writes_selected_columns_with_escaping() {
+    let dir = temp_dir("columns");
+    let path = dir.join("gpu.csv");
+    let options = CsvOptions {
+        columns: CsvColumn::parse_list("device_id,name,utilization_percent,temperature_c")
+            .unwrap(),
+        ..Default::default()
+    };
+    let (info, data) = sample();
+    let mut writer = CsvWriter::create(&path, options).unwrap();
+    writer.write(&info, &data).unwrap();
+    drop(writer);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        text,
+        "device_id,name,utilization_percent,temperature_c\n\
+         0000:03:00.0,\"Radeon, \"\"Pro\"\"\",55,61\n"
+    );
+}
+
+#[test]
+fn unavailable_metrics_are_empty_cells() {
+    let dir = temp_dir("unavailable");
+    let path = dir.join("gpu.csv");
+    let options = CsvOptions {
+        columns: CsvColumn::parse_list("temperature_c,fan_speed_percent,pcie_tx_mb_s").unwrap(),
+        ..Default::default()
+    };
+    let (info, data) = sample();
+    let mut writer = CsvWriter::create(&path, options).unwrap();
+    writer.write(&info, &data).unwrap();
+    drop(writer);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(text, "temperature_c,fan_speed_percent,pcie_tx_mb_s\n61,,\n");
+}
+
+#[test]
+fn rotates_by_size() {
+    let dir = temp_dir("rotate");
+    let path = dir.join("gpu.csv");
+    let options = CsvOptions {
+        columns: vec![CsvColumn::Utilization],
+        rotate_size: Some(1),
+        rotate_interval: None,
+    };
+    let (info, data) = sample();
+    let mut writer = CsvWriter::create(&path, options).unwrap();
+    writer.write(&info, &data).unwrap();
+    writer.write(&info, &data).unwrap();
+    assert_eq!(writer.path(), dir.join("gpu.2.csv"));
+    drop(writer);
+
+    for name in ["gpu.csv", "gpu.1.csv", "gpu.2.csv"] {
+        let text = std::fs::read_to_string(dir.join(name)).unwrap();
+        assert!(text.starts_with("utilization_percent\n"), "{name}: {text}");
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unknown_columns_are_rejected() {
+    assert!(CsvColumn::parse_list("timestamp,bogus").is_err());
+}
+
+#[test]
+fn timestamps_are_rfc3339_utc() {
+    assert_eq!(format_rfc3339(0.0), "1970-01-01T00:00:00.000Z");
+    assert_eq!(format_rfc3339(1_760_486_400.25), "2025-10-15T00:00:00.250Z");
+    assert_eq!(format_rfc3339(951_782_400.0), "2000-02-29T00:00:00.000Z");
+}
+
+#[test]
+fn sizes_accept_binary_suffixes() {
+    assert_eq!(parse_size("512"), Ok(512));
+    assert_eq!(parse_size("64K"), Ok(64 * 1024));
+    assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+    assert!(parse_size("0").is_err());
+    assert!(parse_size("ten").is_err());
+    assert!(parse_size("18446744073709551615G").is_err());
+}
@@ -0,0 +1,157 @@
+// Per-process usage from fake /proc trees under tests/fixtures/proc.
+use rgm_ui::data::ProcessType;
+use rgm_ui::fdinfo::{parse_fdinfo, DrmClients, FdinfoScanner};
+use rgm_ui::monitor::{AmdgpuMonitor, GpuMonitor};
+use std::path::PathBuf;
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(path)
+}
+
+#[test]
+fn parses_legacy_amdgpu_keys() {
+    let text = std::fs::read_to_string(fixture("proc/1200/fdinfo/7")).unwrap();
+    let client = parse_fdinfo(&text).unwrap();
+    assert_eq!(client.driver, "amdgpu");
+    assert_eq!(client.pdev, "0000:03:00.0");
+    assert_eq!(client.client_id, 42);
+    assert_eq!(client.total_engine_ns(), 6_000_000_000);
+    assert_eq!(client.device_memory(), 512 * 1024 * 1024);
+}
+
+#[test]
+fn prefers_resident_memory_and_skips_engine_capacity() {
+    let text = std::fs::read_to_string(fixture("proc/5600/fdinfo/3")).unwrap();
+    let client = parse_fdinfo(&text).unwrap();
+    assert_eq!(client.engine_ns.len(), 1);
+    // No local memory on an iGPU, so system memory is attributed instead
+    assert_eq!(client.device_memory(), 12 * 1024 * 1024);
+}
+
+#[test]
+fn parses_xe_cycle_counters() {
+    let text = std::fs::read_to_string(fixture("proc/7800/fdinfo/4")).unwrap();
+    let client = parse_fdinfo(&text).unwrap();
+    assert_eq!(client.driver, "xe");
+    assert!(client.engine_ns.is_empty());
+    assert_eq!(client.engine_cycles["rcs"], (28257900, 7655183225));
+    assert_eq!(client.engine_cycles.len(), 3);
+    // drm-total-cycles-* is not a memory region
+    assert_eq!(client.device_memory(), 48 * 1024 * 1024);
+    assert_eq!(client.process_type(), Some(ProcessType::GraphicsCompute));
+}
+
+#[test]
+fn xe_utilization_comes_from_cycle_deltas() {
+    let root = std::env::temp_dir().join(format!("rgm-test-{}-xe-cycles", std::process::id()));
+    let fdinfo = root.join("7800/fdinfo");
+    std::fs::create_dir_all(&fdinfo).unwrap();
+    let write = |busy: u64, total: u64| {
+        let text = format!(
+            "drm-driver:\txe\ndrm-client-id:\t51\ndrm-pdev:\t0000:00:02.0\n\
+             drm-cycles-rcs:\t{busy}\ndrm-total-cycles-rcs:\t{total}\n"
+        );
+        std::fs::write(fdinfo.join("4"), text).unwrap();
+    };
+    let scanner = FdinfoScanner::new(&root, "0000:00:02.0");
+
+    write(1000, 10_000);
+    let first = scanner.measure();
+    assert_eq!(first.processes[0].gpu_utilization, 0.0);
+    // Nothing to compare against yet: unknown, not idle
+    assert_eq!(first.busy_percent, None);
+    write(1250, 11_000);
+    let second = scanner.measure();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(second.processes[0].gpu_utilization, 25.0);
+    assert_eq!(second.busy_percent, Some(25.0));
+}
+
+#[test]
+fn idle_device_reads_zero_once_measurable() {
+    let root = std::env::temp_dir().join(format!("rgm-test-{}-idle", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let scanner = FdinfoScanner::new(&root, "0000:00:02.0");
+
+    assert_eq!(scanner.measure().busy_percent, None);
+    // No clients across two readable scans: idle, not unknown
+    assert_eq!(scanner.measure().busy_percent, Some(0.0));
+    std::fs::remove_dir_all(&root).unwrap();
+    // procfs gone: unknown again
+    assert_eq!(scanner.measure().busy_percent, None);
+    assert_eq!(scanner.measure().busy_percent, None);
+}
+
+#[test]
+fn non_drm_fd_is_ignored() {
+    assert!(parse_fdinfo("pos:\t0\nflags:\t02\nmnt_id:\t25\n").is_none());
+}
+
+#[test]
+fn oversized_quantities_are_skipped() {
+    let text = "drm-client-id:\t1\ndrm-memory-vram:\t18446744073709551615 KiB\n";
+    assert!(parse_fdinfo(text).unwrap().memory.is_empty());
+}
+
+#[test]
+fn scanner_attributes_memory_per_pid_for_one_device() {
+    let scanner = FdinfoScanner::new(fixture("proc"), "0000:03:00.0");
+    let processes = scanner.scan();
+
+    let summary: Vec<_> = processes
+        .iter()
+        .map(|p| (p.pid, p.name.as_str(), p.memory_usage))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1200, "blender", 512 * 1024 * 1024),
+            (3400, "python3", 2 * 1024 * 1024 * 1024),
+        ]
+    );
+    // Utilization needs two scans to form a delta
+    assert!(processes.iter().all(|p| p.gpu_utilization == 0.0));
+    // The type follows the engines a client has kept busy
+    assert_eq!(
+        processes[0].process_type,
+        Some(ProcessType::GraphicsCompute)
+    );
+    assert_eq!(processes[1].process_type, None);
+    assert_eq!(processes[1].command_line, "python3 train.py --epochs 10");
+    assert_eq!(processes[1].host_memory, 1 << 30);
+}
+
+#[test]
+fn amdgpu_monitor_reports_fdinfo_processes() {
+    let drm_clients = DrmClients::new(fixture("proc"));
+    let monitor = AmdgpuMonitor::enumerate_in(&fixture("sysfs/dgpu"), &drm_clients)
+        .unwrap()
+        .remove(0);
+    let (_, processes) = monitor.sample().unwrap();
+    assert_eq!(processes.len(), 2);
+}
+
+#[test]
+fn devices_share_one_procfs_walk_per_tick() {
+    let root = std::env::temp_dir().join(format!("rgm-test-{}-shared-walk", std::process::id()));
+    let fdinfo = root.join("900/fdinfo");
+    std::fs::create_dir_all(&fdinfo).unwrap();
+    let client = |id: u32, pdev: &str| {
+        format!("drm-driver:\tamdgpu\ndrm-pdev:\t{pdev}\ndrm-client-id:\t{id}\n")
+    };
+    std::fs::write(fdinfo.join("3"), client(1, "0000:03:00.0")).unwrap();
+    let drm_clients = DrmClients::new(&root);
+    let first = FdinfoScanner::shared(&drm_clients, "0000:03:00.0");
+    let second = FdinfoScanner::shared(&drm_clients, "0000:04:00.0");
+
+    assert_eq!(first.clients().len(), 1);
+    // Written after the walk: the second device still sees the walk's result
+    std::fs::write(fdinfo.join("4"), client(2, "0000:04:00.0")).unwrap();
+    assert!(second.clients().is_empty());
+    // A device asking again starts the next walk
+    assert_eq!(first.clients().len(), 1);
+    assert_eq!(second.clients().len(), 1);
+    std::fs::remove_dir_all(&root).unwrap();
+}
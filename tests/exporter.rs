@@ -0,0 +1,93 @@
+// Prometheus text rendering.
+use rgm_ui::data::{GpuData, GpuInfo, GpuVendor, ProcessInfo};
+use rgm_ui::exporter::{render_metrics, request_path, DeviceSnapshot};
+
+fn snapshot() -> DeviceSnapshot {
+    DeviceSnapshot {
+        info: GpuInfo {
+            vendor: GpuVendor::Nvidia,
+            name: "NVIDIA \"Test\" GPU".into(),
+            uuid: "GPU-1234".into(),
+            bus_id: "00000000:01:00.0".into(),
+            driver_version: "550.54".into(),
+            ..Default::default()
+        },
+        data: Some(GpuData {
+            device_id: "00000000:01:00.0".into(),
+            timestamp: 0.0,
+            utilization: Some(42.0),
+            memory_used: Some(2.0),
+            memory_total: Some(8.0),
+            temperature: Some(70),
+            gpu_clock: Some(1800),
+            memory_clock: Some(7000),
+            power_usage: Some(120.5),
+            power_limit: Some(200.0),
+            fan_speed: Some(35),
+            pcie_throughput_tx: Some(1.0),
+            pcie_throughput_rx: Some(0.5),
+            collection_time: Some(2.5),
+            errors: Vec::new(),
+        }),
+        processes: vec![ProcessInfo {
+            pid: 4242,
+            name: "python".into(),
+            memory_usage: 1048576,
+            ..Default::default()
+        }],
+    }
+}
+
+const LABELS: &str =
+    r#"uuid="GPU-1234",name="NVIDIA \"Test\" GPU",vendor="nvidia",bus_id="00000000:01:00.0""#;
+
+#[test]
+fn every_gauge_is_labelled_by_device() {
+    let text = render_metrics(&[snapshot()]);
+
+    for line in [
+        format!("rgm_gpu_up{{{LABELS}}} 1"),
+        format!("rgm_gpu_utilization_percent{{{LABELS}}} 42"),
+        format!("rgm_gpu_memory_used_bytes{{{LABELS}}} 2147483648"),
+        format!("rgm_gpu_temperature_celsius{{{LABELS}}} 70"),
+        format!("rgm_gpu_power_usage_watts{{{LABELS}}} 120.5"),
+        format!("rgm_gpu_pcie_tx_bytes_per_second{{{LABELS}}} 1048576"),
+        format!("rgm_sample_collection_seconds{{{LABELS}}} 0.0025"),
+        format!("rgm_process_gpu_memory_bytes{{{LABELS},pid=\"4242\",process=\"python\"}} 1048576"),
+    ] {
+        assert!(text.contains(&line), "missing `{line}` in:\n{text}");
+    }
+    assert!(text.contains("# TYPE rgm_gpu_fan_speed_percent gauge"));
+}
+
+#[test]
+fn devices_without_a_sample_only_report_info() {
+    let mut device = snapshot();
+    device.data = None;
+    let text = render_metrics(&[device]);
+
+    assert!(text.contains("rgm_gpu_info{"));
+    assert!(text.contains(&format!("rgm_gpu_up{{{LABELS}}} 0")));
+    assert!(!text.contains("rgm_gpu_utilization_percent{"));
+}
+
+#[test]
+fn query_strings_do_not_change_the_route() {
+    assert_eq!(request_path("GET /metrics HTTP/1.1\r\n"), "/metrics");
+    assert_eq!(request_path("GET /metrics?x=1 HTTP/1.1\r\n"), "/metrics");
+    assert_eq!(request_path(""), "/");
+}
+
+#[test]
+fn unavailable_metrics_have_no_series() {
+    let mut device = snapshot();
+    let data = device.data.as_mut().unwrap();
+    data.fan_speed = None;
+    data.pcie_throughput_tx = None;
+    let text = render_metrics(&[device]);
+
+    assert!(text.contains("# TYPE rgm_gpu_fan_speed_percent gauge"));
+    assert!(!text.contains("rgm_gpu_fan_speed_percent{"));
+    assert!(!text.contains("rgm_gpu_pcie_tx_bytes_per_second{"));
+    assert!(text.contains("rgm_gpu_pcie_rx_bytes_per_second{"));
+}
@@ -0,0 +1,68 @@
+// Tiered sample history and level-of-detail selection.
+use rgm_ui::data::{GpuData, Metric};
+use rgm_ui::history::{History, Resolution};
+use std::time::Duration;
+
+fn sample(timestamp: f64, utilization: f32) -> GpuData {
+    let mut data = GpuData::empty("0000:03:00.0", timestamp);
+    data.utilization = Some(utilization);
+    data
+}
+
+fn utilization(d: &GpuData) -> Option<f64> {
+    d.metric(Metric::Utilization)
+}
+
+#[test]
+fn samples_that_do_not_advance_are_ignored() {
+    let mut history = History::new(Duration::from_secs(60));
+    assert!(history.push(sample(1.0, 10.0)));
+    assert!(!history.push(sample(1.0, 20.0)));
+    assert!(!history.push(sample(0.5, 30.0)));
+    assert_eq!(history.raw().len(), 1);
+    assert_eq!(history.latest().unwrap().utilization, Some(10.0));
+}
+
+#[test]
+fn raw_samples_outside_the_window_are_dropped() {
+    let mut history = History::new(Duration::from_secs(10));
+    for t in 0..=20 {
+        history.push(sample(t as f64, 50.0));
+    }
+    assert_eq!(history.raw().front().unwrap().timestamp, 10.0);
+}
+
+#[test]
+fn rollups_keep_min_avg_and_max() {
+    let mut history = History::new(Duration::from_secs(3600));
+    for i in 0..10 {
+        history.push(sample(i as f64 * 0.1, i as f32 * 10.0));
+    }
+    history.push(sample(1.0, 100.0));
+
+    let (resolution, points) = history.series(2.0, 5, utilization);
+    assert_eq!(resolution, Resolution::Rollup(1));
+    assert_eq!(points.len(), 2);
+    assert_eq!(
+        (points[0].min, points[0].avg, points[0].max),
+        (0.0, 45.0, 90.0)
+    );
+    assert_eq!(points[0].timestamp, 0.5);
+    // The bucket still in progress is included, but not plotted past now
+    assert_eq!(points[1].avg, 100.0);
+    assert_eq!(points[1].timestamp, 1.0);
+}
+
+#[test]
+fn resolution_follows_span_and_point_budget() {
+    let mut history = History::new(Duration::from_secs(24 * 3600));
+    for i in 0..1000 {
+        history.push(sample(i as f64 * 0.1, 50.0));
+    }
+
+    assert_eq!(history.resolution_for(10.0, 1000), Resolution::Raw);
+    assert_eq!(history.resolution_for(100.0, 500), Resolution::Rollup(1));
+    assert_eq!(history.resolution_for(100.0, 50), Resolution::Rollup(60));
+    // Beyond raw and 1 s retention
+    assert_eq!(history.resolution_for(7200.0, 1000), Resolution::Rollup(60));
+}
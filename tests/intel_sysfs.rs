@@ -0,0 +1,65 @@
+// Drive the Intel backend from a fake sysfs tree under tests/fixtures/sysfs.
+use rgm_ui::data::GpuVendor;
+use rgm_ui::fdinfo::DrmClients;
+use rgm_ui::monitor::{GpuMonitor, IntelMonitor};
+use std::path::PathBuf;
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(path)
+}
+
+fn monitors() -> Vec<IntelMonitor> {
+    let drm_clients = DrmClients::new(fixture("proc"));
+    IntelMonitor::enumerate_in(&fixture("sysfs/intel"), &drm_clients).unwrap()
+}
+
+#[test]
+fn discovers_i915_and_xe_cards() {
+    let monitors = monitors();
+    let drivers: Vec<_> = monitors
+        .iter()
+        .map(|m| m.get_static_info().driver_version)
+        .collect();
+    assert_eq!(drivers, ["i915", "xe"]);
+}
+
+#[test]
+fn i915_reads_frequency_lmem_and_energy() {
+    let monitors = monitors();
+    let arc = &monitors[0];
+
+    let info = arc.get_static_info();
+    assert_eq!(info.vendor, GpuVendor::Intel);
+    assert_eq!(info.bus_id, "0000:03:00.0");
+    assert_eq!(info.pcie_gen, 4);
+    assert_eq!(info.pcie_width, 8);
+    assert_eq!(info.max_gpu_clock, Some(2400));
+
+    let (data, _) = arc.sample().unwrap();
+    assert_eq!(data.gpu_clock, Some(2000));
+    assert_eq!(data.memory_total, Some(16.0));
+    assert_eq!(data.memory_used, Some(4.0));
+    assert_eq!(data.power_limit, Some(225.0));
+    // Power is derived from two energy readings; the first has no baseline
+    assert_eq!(data.power_usage, None);
+    assert_eq!(data.memory_clock, None);
+    assert_eq!(data.fan_speed, None);
+}
+
+#[test]
+fn xe_reads_tile_frequency_and_hwmon() {
+    let monitors = monitors();
+    assert_eq!(monitors[1].get_static_info().max_gpu_clock, Some(2050));
+    let (data, processes) = monitors[1].sample().unwrap();
+    assert_eq!(data.gpu_clock, Some(1450));
+    assert_eq!(data.temperature, Some(51));
+    assert_eq!(data.power_usage, Some(7.5));
+    assert_eq!(data.memory_total, None);
+    // Engine time needs a second sample to form a delta
+    assert_eq!(data.utilization, None);
+    assert!(data.errors.is_empty(), "{:?}", data.errors);
+    let names: Vec<_> = processes.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["Xorg", "kwin_wayland"]);
+}
@@ -0,0 +1,134 @@
+// The NDJSON record layout is a public interface; these tests pin it.
+use rgm_ui::data::{
+    GpuData, GpuInfo, GpuVendor, Metric, MetricError, ProcessInfo, ProcessType, SampleRecord,
+    SCHEMA_VERSION,
+};
+use serde_json::{json, Value};
+
+fn sample_record() -> SampleRecord {
+    let device = GpuInfo {
+        vendor: GpuVendor::Amd,
+        name: "AMD GPU [1002:73BF]".into(),
+        uuid: "N/A".into(),
+        bus_id: "0000:03:00.0".into(),
+        pcie_gen: 4,
+        pcie_width: 16,
+        driver_version: "amdgpu".into(),
+        vbios_version: "113-D4120100-100".into(),
+        max_gpu_clock: None,
+    };
+    let sample = GpuData {
+        device_id: "0000:03:00.0".into(),
+        timestamp: 1.5,
+        utilization: Some(87.0),
+        memory_used: Some(4.0),
+        memory_total: Some(16.0),
+        temperature: Some(65),
+        gpu_clock: Some(2100),
+        memory_clock: Some(1000),
+        power_usage: Some(180.0),
+        power_limit: Some(250.0),
+        fan_speed: Some(50),
+        pcie_throughput_tx: None,
+        pcie_throughput_rx: None,
+        collection_time: None,
+        errors: Vec::new(),
+    };
+    let processes = vec![ProcessInfo {
+        pid: 1200,
+        name: "blender".into(),
+        memory_usage: 536870912,
+        user: "alice".into(),
+        command_line: "blender -b scene.blend".into(),
+        host_memory: 268435456,
+        cpu_percent: 0.0,
+        gpu_utilization: 12.5,
+        process_type: Some(ProcessType::Graphics),
+        ..Default::default()
+    }];
+    SampleRecord::new(device, sample, processes)
+}
+
+#[test]
+fn record_serializes_with_documented_field_names() {
+    let value = serde_json::to_value(sample_record()).unwrap();
+
+    assert_eq!(value["schema_version"], json!(SCHEMA_VERSION));
+    assert!(value["unix_time"].as_f64().unwrap() > 0.0);
+    assert_eq!(value["device"]["vendor"], json!("amd"));
+    assert_eq!(value["device"]["bus_id"], json!("0000:03:00.0"));
+    assert_eq!(value["sample"]["device_id"], json!("0000:03:00.0"));
+    assert_eq!(value["sample"]["memory_used"], json!(4.0));
+    assert_eq!(value["sample"]["temperature"], json!(65));
+    assert_eq!(value["sample"]["pcie_throughput_tx"], Value::Null);
+    assert!(value["sample"].get("errors").is_none());
+    assert_eq!(value["processes"][0]["pid"], json!(1200));
+    assert_eq!(value["processes"][0]["memory_usage"], json!(536870912));
+    assert_eq!(value["processes"][0]["host_memory"], json!(268435456));
+    assert_eq!(value["processes"][0]["process_type"], json!("graphics"));
+    assert_eq!(value["processes"][0]["sm_utilization"], Value::Null);
+
+    let keys = |v: &Value| {
+        let mut keys: Vec<_> = v.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    };
+    assert_eq!(
+        keys(&value),
+        [
+            "device",
+            "processes",
+            "sample",
+            "schema_version",
+            "unix_time"
+        ]
+    );
+}
+
+#[test]
+fn record_round_trips() {
+    let record = sample_record();
+    let line = serde_json::to_string(&record).unwrap();
+    assert!(!line.contains('\n'), "NDJSON records must be single-line");
+
+    let parsed: SampleRecord = serde_json::from_str(&line).unwrap();
+    assert_eq!(parsed.device.vendor, GpuVendor::Amd);
+    assert_eq!(parsed.sample.gpu_clock, Some(2100));
+    assert_eq!(parsed.sample.pcie_throughput_rx, None);
+    assert_eq!(parsed.processes[0].name, "blender");
+    assert_eq!(parsed.processes[0].user, "alice");
+    assert_eq!(
+        parsed.processes[0].process_type,
+        Some(ProcessType::Graphics)
+    );
+}
+
+#[test]
+fn processes_without_host_details_still_parse() {
+    // As written before the user, command line and type fields existed
+    let process: ProcessInfo = serde_json::from_value(json!({
+        "pid": 1200, "name": "blender", "memory_usage": 536870912,
+        "cpu_percent": 0.0, "gpu_utilization": 12.5
+    }))
+    .unwrap();
+    assert_eq!(process.user, "");
+    assert_eq!(process.host_memory, 0);
+    assert_eq!(process.process_type, None);
+}
+
+#[test]
+fn read_errors_are_serialized_per_metric() {
+    let mut record = sample_record();
+    record.sample.temperature = None;
+    record.sample.errors.push(MetricError {
+        metric: Metric::Temperature,
+        message: "permission denied".into(),
+    });
+    let value = serde_json::to_value(&record).unwrap();
+
+    assert_eq!(value["sample"]["temperature"], Value::Null);
+    assert_eq!(
+        value["sample"]["errors"],
+        json!([{ "metric": "temperature", "message": "permission denied" }])
+    );
+}
@@ -0,0 +1,160 @@
+// Host process details from fake /proc trees, sorting and filtering.
+use rgm_ui::data::{ProcessInfo, ProcessType};
+use rgm_ui::processes::{
+    matches_filter, merge_processes, parse_cmdline, parse_cpu_ticks, parse_passwd, parse_status,
+    visible_processes, HostProcesses, ProcessSort, SortKey,
+};
+use std::path::PathBuf;
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(path)
+}
+
+fn process(pid: u32, name: &str, memory_mib: u64, gpu: f32) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        name: name.into(),
+        memory_usage: memory_mib << 20,
+        gpu_utilization: gpu,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parses_procfs_files() {
+    let status = "Name:\tbash\nUid:\t1000\t1000\t1000\t1000\nVmRSS:\t    5120 kB\n";
+    assert_eq!(parse_status(status), (Some(1000), Some(5 << 20)));
+    // Kernel threads have no VmRSS
+    assert_eq!(parse_status("Uid:\t0\t0\t0\t0\n"), (Some(0), None));
+
+    let stat = "42 (a) b (c)) S 1 42 42 0 -1 0 0 0 0 0 30 12 0 0 20 0 1 0 5";
+    assert_eq!(parse_cpu_ticks(stat), Some(42));
+    assert_eq!(parse_cpu_ticks("42 (truncated"), None);
+
+    assert_eq!(
+        parse_cmdline(b"python3\0-m\0torch.distributed\0"),
+        "python3 -m torch.distributed"
+    );
+    assert_eq!(parse_cmdline(b""), "");
+
+    let users = parse_passwd("root:x:0:0::/root:/bin/sh\n# comment\nbob:x:1001:1001::/:/bin/sh\n");
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[&1001], "bob");
+}
+
+#[test]
+fn host_details_come_from_procfs() {
+    let host = HostProcesses::new(fixture("proc")).with_passwd(fixture("passwd"));
+    let mut processes = vec![
+        process(1200, "blender", 512, 0.0),
+        process(3400, "python3", 2048, 0.0),
+        process(9999, "gone", 1, 0.0),
+    ];
+    host.fill(&mut processes);
+
+    assert_eq!(processes[0].user, "alice");
+    assert_eq!(processes[0].command_line, "blender -b scene.blend");
+    assert_eq!(processes[0].host_memory, 256 << 20);
+    // No passwd entry: the uid stands in for the name
+    assert_eq!(processes[1].user, "1001");
+    // Processes that exited keep what the backend reported
+    assert_eq!(processes[2].user, "");
+    assert_eq!(processes[2].command_line, "");
+
+    // CPU usage needs two reads; the fixture's counters do not move
+    host.fill(&mut processes);
+    assert!(processes.iter().all(|p| p.cpu_percent == 0.0));
+}
+
+#[test]
+fn processes_sort_by_the_selected_column() {
+    let mut processes = vec![
+        process(30, "blender", 512, 5.0),
+        process(10, "Xorg", 128, 40.0),
+        process(20, "python", 2048, 40.0),
+    ];
+    processes[0].sm_utilization = Some(3.0);
+    let pids = |p: &[ProcessInfo]| p.iter().map(|p| p.pid).collect::<Vec<_>>();
+
+    let mut sort = ProcessSort::default();
+    sort.apply(&mut processes);
+    assert_eq!(pids(&processes), [20, 30, 10]);
+
+    sort.select(SortKey::Name);
+    assert!(!sort.descending);
+    sort.apply(&mut processes);
+    assert_eq!(pids(&processes), [30, 20, 10]);
+
+    // Ties fall back to PID order
+    sort.select(SortKey::Gpu);
+    sort.apply(&mut processes);
+    assert_eq!(pids(&processes), [10, 20, 30]);
+
+    // Missing values sort below present ones
+    sort.select(SortKey::Sm);
+    sort.apply(&mut processes);
+    assert_eq!(pids(&processes), [30, 10, 20]);
+
+    // Selecting the same column again reverses it
+    sort.select(SortKey::Sm);
+    assert!(!sort.descending);
+}
+
+#[test]
+fn filter_matches_pid_name_user_or_command() {
+    let mut trainer = process(4242, "python3", 1024, 90.0);
+    trainer.user = "alice".into();
+    trainer.command_line = "python3 train.py --model ResNet".into();
+    trainer.process_type = Some(ProcessType::Compute);
+    let processes = vec![trainer, process(77, "Xorg", 64, 2.0)];
+
+    assert!(matches_filter(&processes[0], ""));
+    assert!(matches_filter(&processes[0], "424"));
+    assert!(matches_filter(&processes[0], "ALICE"));
+    assert!(matches_filter(&processes[0], "resnet"));
+    assert!(!matches_filter(&processes[1], "alice"));
+
+    let visible = visible_processes(&processes, " xorg ", ProcessSort::default());
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].pid, 77);
+}
+
+#[test]
+fn process_types_merge_and_label_like_nvidia_smi() {
+    let graphics = ProcessType::Graphics;
+    assert_eq!(graphics.merge(ProcessType::Graphics), ProcessType::Graphics);
+    assert_eq!(
+        graphics.merge(ProcessType::Compute),
+        ProcessType::GraphicsCompute
+    );
+    assert_eq!(ProcessType::GraphicsCompute.label(), "C+G");
+}
+
+#[test]
+fn graphics_and_compute_entries_of_one_pid_are_merged() {
+    let typed = |pid, memory_mib, process_type| ProcessInfo {
+        process_type: Some(process_type),
+        ..process(pid, "python3", memory_mib, 0.0)
+    };
+    let merged = merge_processes(vec![
+        typed(300, 1024, ProcessType::Compute),
+        typed(100, 2048, ProcessType::Compute),
+        typed(100, 2048, ProcessType::Graphics),
+        typed(200, 64, ProcessType::Graphics),
+    ]);
+
+    let summary: Vec<_> = merged
+        .iter()
+        .map(|p| (p.pid, p.memory_usage >> 20, p.process_type))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (100, 2048, Some(ProcessType::GraphicsCompute)),
+            (200, 64, Some(ProcessType::Graphics)),
+            (300, 1024, Some(ProcessType::Compute)),
+        ]
+    );
+}
@@ -0,0 +1,155 @@
+// Recording round trip and replay through the GpuMonitor trait.
+use rgm_ui::cli::CliOptions;
+use rgm_ui::data::{GpuData, GpuInfo, GpuVendor};
+use rgm_ui::monitor::{Backend, GpuMonitor, MonitorError};
+use rgm_ui::recording::{load_monitors, load_supervised, Recorder, Recording, RecordingError};
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rgm-test-{}-{name}", std::process::id()))
+}
+
+fn info(bus_id: &str) -> GpuInfo {
+    GpuInfo {
+        vendor: GpuVendor::Amd,
+        name: "AMD GPU".into(),
+        bus_id: bus_id.into(),
+        ..Default::default()
+    }
+}
+
+fn sample(device_id: &str, timestamp: f64, utilization: f32) -> GpuData {
+    GpuData {
+        device_id: device_id.into(),
+        timestamp,
+        utilization: Some(utilization),
+        memory_used: Some(1.0),
+        memory_total: Some(8.0),
+        temperature: Some(50),
+        gpu_clock: Some(1000),
+        memory_clock: Some(800),
+        power_usage: Some(50.0),
+        power_limit: Some(100.0),
+        fan_speed: None,
+        pcie_throughput_tx: None,
+        pcie_throughput_rx: None,
+        collection_time: None,
+        errors: Vec::new(),
+    }
+}
+
+#[test]
+fn recording_round_trips_and_replays_per_device() {
+    let path = temp_path("roundtrip.ndjson");
+    let mut recorder =
+        Recorder::create(&path, vec![info("0000:03:00.0"), info("0000:05:00.0")]).unwrap();
+    recorder
+        .record(&sample("0000:03:00.0", 0.1, 10.0), &[])
+        .unwrap();
+    recorder
+        .record(&sample("0000:05:00.0", 0.1, 20.0), &[])
+        .unwrap();
+    recorder
+        .record(&sample("0000:03:00.0", 0.2, 30.0), &[])
+        .unwrap();
+    drop(recorder);
+
+    let recording = Recording::open(&path).unwrap();
+    assert_eq!(recording.header.devices.len(), 2);
+
+    // Replay fast enough that every sample is already due
+    let monitors = recording.into_monitors(1e9);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(monitors[0].get_static_info().bus_id, "0000:03:00.0");
+    let utilization = |batch: Vec<(GpuData, _)>| -> Vec<_> {
+        batch
+            .into_iter()
+            .map(|(data, _)| data.utilization)
+            .collect()
+    };
+    // Every due sample is delivered, not only the latest
+    assert_eq!(
+        utilization(monitors[0].sample_batch().unwrap()),
+        [Some(10.0), Some(30.0)]
+    );
+    assert_eq!(monitors[1].sample().unwrap().0.utilization, Some(20.0));
+    // Past the end, the final sample is repeated
+    assert_eq!(monitors[0].sample().unwrap().0.utilization, Some(30.0));
+}
+
+#[test]
+fn devices_attached_while_recording_join_the_replay() {
+    let path = temp_path("attached.ndjson");
+    let mut recorder = Recorder::create(&path, vec![info("0000:03:00.0")]).unwrap();
+    recorder
+        .record(&sample("0000:03:00.0", 0.1, 10.0), &[])
+        .unwrap();
+    recorder.add_device(&info("0000:05:00.0")).unwrap();
+    recorder
+        .record(&sample("0000:05:00.0", 0.2, 20.0), &[])
+        .unwrap();
+    drop(recorder);
+
+    let options = CliOptions {
+        replay: Some(path.clone()),
+        speed: 1e9,
+        ..Default::default()
+    };
+    let (report, probe, _) = load_supervised(&options);
+    assert_eq!(report.monitors.len(), 1);
+    let attached = probe();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(attached.len(), 1);
+    assert_eq!(attached[0].get_static_info().bus_id, "0000:05:00.0");
+    assert_eq!(attached[0].sample().unwrap().0.utilization, Some(20.0));
+    // Each attached device is handed out once
+    assert!(probe().is_empty());
+}
+
+#[test]
+fn other_files_are_rejected() {
+    let path = temp_path("not-a-recording.json");
+    std::fs::write(&path, "{\"hello\": \"world\"}\n").unwrap();
+    let result = Recording::open(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(RecordingError::NotARecording)));
+}
+
+#[test]
+fn device_filter_selects_replayed_devices() {
+    let path = temp_path("filter.ndjson");
+    let mut recorder =
+        Recorder::create(&path, vec![info("0000:03:00.0"), info("0000:05:00.0")]).unwrap();
+    recorder
+        .record(&sample("0000:05:00.0", 0.1, 20.0), &[])
+        .unwrap();
+    drop(recorder);
+
+    let mut options = CliOptions {
+        replay: Some(path.clone()),
+        devices: vec!["0000:05:00.0".into()],
+        ..Default::default()
+    };
+    let report = load_monitors(&options);
+    assert_eq!(report.monitors.len(), 1);
+    assert_eq!(report.monitors[0].get_static_info().bus_id, "0000:05:00.0");
+
+    options.devices = vec!["nvidia".into()];
+    let report = load_monitors(&options);
+    std::fs::remove_file(&path).unwrap();
+    assert!(report.monitors.is_empty());
+    assert_eq!(report.failures[0].backend, Backend::DeviceFilter);
+    assert!(matches!(
+        &report.failures[0].error,
+        MonitorError::NoMatchingDevice(patterns) if patterns == "nvidia"
+    ));
+    assert!(report.failures[0].remedy().contains("--device"));
+
+    options.replay = Some(temp_path("absent.rgm"));
+    let report = load_monitors(&options);
+    assert_eq!(report.failures[0].backend, Backend::Replay);
+    assert!(matches!(
+        report.failures[0].error,
+        MonitorError::Recording(_)
+    ));
+}
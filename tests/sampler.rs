@@ -0,0 +1,283 @@
+// Background sampling: device histories, hotplug, reconnection and errors.
+use rgm_ui::alerts::{AlertAction, AlertEngine, AlertMetric, AlertRule, Comparator};
+use rgm_ui::data::{GpuData, GpuInfo, Metric, ProcessInfo};
+use rgm_ui::monitor::{GpuMonitor, MonitorError};
+use rgm_ui::sampler::{DeviceSet, DeviceStatus, ErrorLog, ReportedError, Sampler};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+// Counts up one utilization point per sample
+struct Counter {
+    bus_id: &'static str,
+    next: AtomicU32,
+}
+
+impl GpuMonitor for Counter {
+    fn get_static_info(&self) -> GpuInfo {
+        GpuInfo {
+            bus_id: self.bus_id.into(),
+            ..Default::default()
+        }
+    }
+
+    fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError> {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut data = GpuData::empty(self.bus_id, n as f64);
+        data.utilization = Some(n as f32);
+        let worker = ProcessInfo {
+            pid: n,
+            name: "worker".into(),
+            ..Default::default()
+        };
+        Ok((data, vec![worker]))
+    }
+}
+
+#[test]
+fn sampler_fills_each_device_history() {
+    let monitors: Vec<Box<dyn GpuMonitor>> = ["0000:01:00.0", "0000:02:00.0"]
+        .into_iter()
+        .map(|bus_id| {
+            Box::new(Counter {
+                bus_id,
+                next: AtomicU32::new(0),
+            }) as Box<dyn GpuMonitor>
+        })
+        .collect();
+    let window = Duration::from_secs(60);
+    let mut sampler = Sampler::start(
+        monitors,
+        Duration::from_millis(5),
+        window,
+        None,
+        None,
+        AlertEngine::default(),
+    );
+    assert_eq!(sampler.device_index("0000:02:00.0"), Some(1));
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while sampler.devices.iter().any(|d| d.history.raw().len() < 3) {
+        assert!(Instant::now() < deadline, "sampler stalled");
+        sampler.update(window);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    for device in &sampler.devices {
+        let latest = device.history.latest().unwrap();
+        assert_eq!(latest.device_id, device.gpu_info.bus_id);
+        assert_eq!(device.processes[0].pid as f64, latest.timestamp);
+    }
+}
+
+#[test]
+fn devices_can_be_added_after_starting_empty() {
+    let window = Duration::from_secs(60);
+    let mut sampler = Sampler::start(
+        Vec::new(),
+        Duration::from_millis(5),
+        window,
+        None,
+        None,
+        AlertEngine::default(),
+    );
+    assert!(sampler.devices.is_empty());
+    assert!(!sampler.update(window));
+
+    sampler.add(Box::new(Counter {
+        bus_id: "0000:03:00.0",
+        next: AtomicU32::new(0),
+    }));
+    assert_eq!(sampler.device_index("0000:03:00.0"), Some(0));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !sampler.update(window) {
+        assert!(Instant::now() < deadline, "added device is not sampled");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    // A reload changes the interval the TUI header reports
+    sampler.set_interval(Duration::from_secs(2));
+    assert_eq!(sampler.interval(), Duration::from_secs(2));
+}
+
+#[test]
+fn sampling_goes_on_while_the_ui_is_not_reading() {
+    // Fires once the alerts have seen more samples than the UI queue holds
+    let rule = AlertRule {
+        name: "busy".into(),
+        metric: AlertMetric::Metric(Metric::Utilization),
+        comparator: Comparator::Above,
+        threshold: 1500.0,
+        sustained: Duration::ZERO,
+        hysteresis: 0.0,
+        actions: vec![AlertAction::Highlight],
+    };
+    let counter = Counter {
+        bus_id: "0000:01:00.0",
+        next: AtomicU32::new(0),
+    };
+    let window = Duration::from_secs(3600);
+    let mut sampler = Sampler::start(
+        vec![Box::new(counter)],
+        Duration::ZERO,
+        window,
+        None,
+        None,
+        AlertEngine::new(vec![rule]),
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while sampler.alerts.lock().unwrap().firing().count() == 0 {
+        assert!(Instant::now() < deadline, "sampling stalled on the UI");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(sampler.update(window));
+}
+
+// Samples a few times, then fails like a GPU that fell off the bus
+struct Flaky {
+    samples_left: AtomicU32,
+}
+
+impl GpuMonitor for Flaky {
+    fn get_static_info(&self) -> GpuInfo {
+        GpuInfo {
+            bus_id: "0000:01:00.0".into(),
+            ..Default::default()
+        }
+    }
+
+    fn sample(&self) -> Result<(GpuData, Vec<ProcessInfo>), MonitorError> {
+        let left = self.samples_left.load(Ordering::Relaxed);
+        if left == 0 {
+            return Err(MonitorError::SamplingFailed("GPU is lost".into()));
+        }
+        self.samples_left.store(left - 1, Ordering::Relaxed);
+        let timestamp = 1000.0 - left as f64;
+        Ok((GpuData::empty("0000:01:00.0", timestamp), Vec::new()))
+    }
+}
+
+#[test]
+fn supervisor_reconnects_lost_devices_and_adds_new_ones() {
+    let window = Duration::from_secs(60);
+    let flaky = Flaky {
+        samples_left: AtomicU32::new(2),
+    };
+    let mut sampler = Sampler::start(
+        vec![Box::new(flaky)],
+        Duration::from_millis(5),
+        window,
+        None,
+        None,
+        AlertEngine::default(),
+    );
+    sampler.supervise(
+        || {
+            ["0000:01:00.0", "0000:02:00.0"]
+                .into_iter()
+                .map(|bus_id| {
+                    Box::new(Counter {
+                        bus_id,
+                        next: AtomicU32::new(0),
+                    }) as Box<dyn GpuMonitor>
+                })
+                .collect()
+        },
+        Duration::from_millis(20),
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut went_offline = false;
+    loop {
+        assert!(Instant::now() < deadline, "device not reconnected");
+        sampler.update(window);
+        let lost = &sampler.devices[0];
+        match &lost.status {
+            DeviceStatus::Offline(error) => {
+                assert!(error.contains("GPU is lost"));
+                went_offline = true;
+            }
+            // The replacement monitor's clock starts over at 0, but its
+            // samples must still follow the lost monitor's in the history
+            DeviceStatus::Online if went_offline => {
+                let latest = lost.history.latest().unwrap();
+                if latest.utilization.is_some() && sampler.devices.len() == 2 {
+                    assert!(latest.timestamp > 999.0);
+                    break;
+                }
+            }
+            DeviceStatus::Online => {}
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(sampler.device_index("0000:02:00.0"), Some(1));
+    // The failures taking it offline are counted; while offline it is left
+    // to the supervisor rather than sampled on every tick
+    assert_eq!(sampler.errors.counts["sampling"], 3);
+    assert!(sampler.errors.last.is_some());
+}
+
+#[test]
+fn error_log_counts_by_kind() {
+    let mut log = ErrorLog::default();
+    let error = |kind, message: &str| ReportedError {
+        device_id: "0000:01:00.0".into(),
+        kind,
+        message: message.into(),
+    };
+    log.report(error("metric read", "reading fan speed failed: EIO"));
+    log.report(error("device lost", "the GPU is lost"));
+    log.report(error("metric read", "reading power usage failed: EIO"));
+
+    assert_eq!(log.total(), 3);
+    assert_eq!(log.summary(), "3 errors (1 device lost, 2 metric read)");
+    assert_eq!(
+        log.last.unwrap().to_string(),
+        "GPU 0000:01:00.0: reading power usage failed: EIO"
+    );
+}
+
+#[test]
+fn device_sets_reconnect_lost_devices_and_add_new_ones() {
+    let flaky = Flaky {
+        samples_left: AtomicU32::new(1),
+    };
+    let mut devices = DeviceSet::new(vec![Box::new(flaky)]);
+    devices.supervise(
+        || {
+            ["0000:01:00.0", "0000:02:00.0"]
+                .into_iter()
+                .map(|bus_id| {
+                    Box::new(Counter {
+                        bus_id,
+                        next: AtomicU32::new(0),
+                    }) as Box<dyn GpuMonitor>
+                })
+                .collect()
+        },
+        Duration::from_millis(20),
+    );
+
+    let lost = &mut devices.devices[0].1;
+    assert!(matches!(lost.sample(), Some(Ok(_))));
+    for _ in 0..3 {
+        assert!(matches!(lost.sample(), Some(Err(_))));
+    }
+    assert!(lost.is_offline());
+    // Left alone until the supervisor's probe brings a new monitor
+    assert!(lost.sample().is_none());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let batch = loop {
+        assert!(Instant::now() < deadline, "device not reconnected");
+        devices.reconnect();
+        if let Some(result) = devices.devices[0].1.sample() {
+            break result.unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    };
+    assert!(!devices.devices[0].1.is_offline());
+    // The replacement's clock starts at 0, after the lost monitor's 999
+    assert!(batch[0].0.timestamp > 999.0);
+    assert_eq!(devices.devices.len(), 2);
+    assert_eq!(devices.devices[1].0.bus_id, "0000:02:00.0");
+}
@@ -0,0 +1,46 @@
+// GUI settings: command-line overrides and clamping.
+use rgm_ui::cli::CliOptions;
+use rgm_ui::settings::{Settings, MAX_HISTORY, MIN_SAMPLE_INTERVAL};
+use std::time::Duration;
+
+#[test]
+fn command_line_overrides_stored_settings() {
+    let stored = Settings {
+        sample_interval: Duration::from_secs(2),
+        history: Duration::from_secs(600),
+        ..Default::default()
+    };
+    let options = CliOptions::parse(["--interval", "500ms"]).unwrap();
+    let settings = stored.with_cli(&options);
+    assert_eq!(settings.sample_interval, Duration::from_millis(500));
+    assert_eq!(settings.history, Duration::from_secs(600));
+}
+
+#[test]
+fn command_line_overrides_are_not_persisted() {
+    let stored = Settings {
+        sample_interval: Duration::from_secs(2),
+        ..Default::default()
+    };
+    let options = CliOptions::parse(["--interval", "500ms"]).unwrap();
+    let mut settings = stored.clone().with_cli(&options);
+    settings.hidden_plots.insert("power".into());
+
+    let persisted = settings.persisted(&stored);
+    assert_eq!(persisted.sample_interval, Duration::from_secs(2));
+    assert!(persisted.hidden_plots.contains("power"));
+}
+
+#[test]
+fn out_of_range_values_are_clamped() {
+    let options = CliOptions::parse(["--interval", "1ms", "--history", "48h"]).unwrap();
+    let settings = Settings::default().with_cli(&options);
+    assert_eq!(settings.sample_interval, MIN_SAMPLE_INTERVAL);
+    assert_eq!(settings.history, MAX_HISTORY);
+}
+
+#[test]
+fn settings_missing_from_storage_use_defaults() {
+    let settings: Settings = serde_json::from_str("{}").unwrap();
+    assert_eq!(settings, Settings::default());
+}
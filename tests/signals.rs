@@ -0,0 +1,66 @@
+// Signalling processes from the process table and `rgm kill`.
+use rgm_ui::signals::{send, Signal, SignalError};
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Process state letter from `/proc/<pid>/stat`, e.g. `S` or `T`.
+fn state(pid: u32) -> char {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+    let (_, fields) = stat.rsplit_once(')').unwrap();
+    fields.trim_start().chars().next().unwrap()
+}
+
+fn wait_for_state(pid: u32, wanted: impl Fn(char) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !wanted(state(pid)) {
+        assert!(
+            Instant::now() < deadline,
+            "PID {pid} stayed in state {}",
+            state(pid)
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn signal_names_and_numbers_parse() {
+    assert_eq!(Signal::parse("term"), Some(Signal::Term));
+    assert_eq!(Signal::parse("SIGKILL"), Some(Signal::Kill));
+    assert_eq!(Signal::parse("9"), Some(Signal::Kill));
+    assert_eq!(Signal::parse(" cont "), Some(Signal::Cont));
+    assert_eq!(Signal::parse("HUP"), None);
+    assert_eq!(Signal::Stop.to_string(), "SIGSTOP");
+}
+
+#[test]
+fn stop_continue_and_kill_a_child() {
+    let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+    let pid = child.id();
+
+    send(pid, Signal::Stop).unwrap();
+    wait_for_state(pid, |s| s == 'T');
+    send(pid, Signal::Cont).unwrap();
+    wait_for_state(pid, |s| s != 'T');
+    send(pid, Signal::Kill).unwrap();
+    assert_eq!(child.wait().unwrap().signal(), Some(9));
+
+    // Reaped, so the PID is gone
+    assert!(matches!(
+        send(pid, Signal::Term),
+        Err(SignalError::NoSuchProcess(p)) if p == pid
+    ));
+}
+
+#[test]
+fn process_groups_cannot_be_targeted() {
+    // kill(2) treats 0 and negative PIDs as groups, or every process
+    assert!(matches!(
+        send(0, Signal::Term),
+        Err(SignalError::InvalidPid(0))
+    ));
+    assert!(matches!(
+        send(u32::MAX, Signal::Term),
+        Err(SignalError::InvalidPid(_))
+    ));
+}
@@ -0,0 +1,44 @@
+// Terminal UI drawing helpers.
+use rgm_ui::history::SeriesPoint;
+use rgm_ui::tui::{columns, gauge, sparkline};
+
+fn point(timestamp: f64, value: f64) -> SeriesPoint {
+    SeriesPoint {
+        timestamp,
+        min: value,
+        avg: value,
+        max: value,
+    }
+}
+
+#[test]
+fn gauges_fill_proportionally_and_clamp() {
+    assert_eq!(gauge(0.5, 4), "██░░");
+    assert_eq!(gauge(0.0, 3), "░░░");
+    assert_eq!(gauge(1.7, 3), "███");
+    assert_eq!(gauge(-1.0, 2), "░░");
+}
+
+#[test]
+fn sparklines_scale_to_the_maximum() {
+    let values = [None, Some(0.0), Some(1.0), Some(50.0), Some(100.0)];
+    assert_eq!(sparkline(&values, 100.0), "  ▁▄█");
+    // Without a scale nothing can be drawn
+    assert_eq!(sparkline(&values, 0.0), "     ");
+}
+
+#[test]
+fn points_are_binned_into_columns_by_time() {
+    let points = [
+        point(0.0, 5.0),
+        point(1.0, 10.0),
+        point(6.0, 20.0),
+        point(7.0, 30.0),
+        point(10.0, 40.0),
+    ];
+    // 10 seconds over 5 columns: two seconds each, the peak wins
+    let binned = columns(&points, 10.0, 10.0, 5);
+    assert_eq!(binned, vec![Some(10.0), None, None, Some(30.0), Some(40.0)]);
+    // Points older than the span are left out
+    assert_eq!(columns(&points, 10.0, 4.0, 2), vec![Some(30.0), Some(40.0)]);
+}